@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+
+use crate::errors::ApiError;
+use crate::models::User;
+use crate::pagination::{MAX_OFFSET, MAX_PAGE_SIZE};
+use crate::storage::UserPage;
+
+/// Query string of `GET /admin/users`.
+#[derive(Deserialize)]
+pub struct UserListParams {
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+impl UserListParams {
+    /// Rejects an offset the databases cannot represent, as task listings do.
+    pub fn validate(&self) -> Result<(), ApiError> {
+        if self.offset.is_some_and(|offset| offset > MAX_OFFSET) {
+            return Err(ApiError::InvalidRequest(vec![format!("'offset' must be at most {}", MAX_OFFSET)]));
+        }
+        Ok(())
+    }
+
+    pub fn limit(&self) -> u64 {
+        self.limit.unwrap_or(MAX_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+    }
+
+    pub fn offset(&self) -> u64 {
+        self.offset.unwrap_or(0)
+    }
+}
+
+#[derive(Serialize)]
+pub struct UserPageResponse {
+    pub users: Vec<User>,
+    pub total: u64,
+    pub limit: u64,
+    pub offset: u64,
+}
+
+impl UserPageResponse {
+    pub fn new(page: UserPage, params: &UserListParams) -> Self {
+        UserPageResponse { users: page.users, total: page.total, limit: params.limit(), offset: params.offset() }
+    }
+}
+
+/// Request body of `POST /admin/tasks/{id}/transfer`.
+#[derive(Deserialize)]
+pub struct TransferRequest {
+    pub owner_id: String,
+}
@@ -0,0 +1,410 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::OnceLock;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::http::{header, Method};
+use actix_web::middleware::Next;
+use actix_web::{web, FromRequest, HttpMessage, HttpRequest};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use mongodb::bson::oid::ObjectId;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::errors::ApiError;
+use crate::jwt::{Claims, JwtSettings};
+use crate::models::{ApiKey, Role, Scope, User};
+use crate::storage::{RefreshToken, Rotation};
+use crate::tenant::TenantState;
+use crate::AppState;
+
+/// Prepended to API keys so that they are recognizable, e.g. by secret
+/// scanners.
+const API_KEY_PREFIX: &str = "tdk_";
+
+/// `last_used_at` of an API key is only written when it is at least this old,
+/// so that a busy key does not cost a write per request.
+const LAST_USED_RESOLUTION_SECONDS: i64 = 60;
+
+/// Lifetime of a refresh token unless `REFRESH_TOKEN_TTL_SECONDS` says
+/// otherwise. Each refresh starts the period again.
+pub const DEFAULT_REFRESH_TTL_SECONDS: i64 = 30 * 24 * 60 * 60;
+
+/// Authentication settings, registered as app data next to
+/// [`Tenants`](crate::tenant::Tenants).
+pub struct AuthConfig {
+    pub jwt: JwtSettings,
+    pub refresh_ttl: Duration,
+    /// Emails from `ADMIN_EMAILS`, whose accounts are given the admin role.
+    pub admin_emails: Vec<String>,
+}
+
+impl AuthConfig {
+    /// The role of a new account registered with `email`.
+    pub fn initial_role(&self, email: &str) -> Role {
+        if self.admin_emails.iter().any(|admin| admin == email) {
+            Role::Admin
+        } else {
+            Role::User
+        }
+    }
+}
+
+/// Tokens handed out by `POST /auth/login` and `POST /auth/refresh`.
+#[derive(Serialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: &'static str,
+    /// Seconds until the access token expires.
+    pub expires_in: i64,
+    pub refresh_token: String,
+    pub refresh_expires_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct LoginResponse {
+    #[serde(flatten)]
+    pub tokens: TokenResponse,
+    pub user: User,
+}
+
+/// Response of `POST /auth/api-keys`, the only one carrying the key itself.
+#[derive(Serialize)]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    pub key: String,
+}
+
+/// Request body of `POST /auth/refresh`.
+#[derive(Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0; N];
+    rand::rng().fill_bytes(&mut bytes);
+    bytes
+}
+
+/// Hashes a password with Argon2id on the blocking thread pool, since hashing
+/// is deliberately slow.
+pub async fn hash_password(password: String) -> Result<String, ApiError> {
+    web::block(move || {
+        let salt = SaltString::encode_b64(&random_bytes::<16>()).map_err(|err| err.to_string())?;
+        Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|err| err.to_string())
+    })
+    .await
+    .map_err(|err| ApiError::Internal(err.to_string()))?
+    .map_err(ApiError::Internal)
+}
+
+/// A hash no password matches, checked against when the email is unknown so
+/// that failed logins take equally long whether or not the account exists.
+fn decoy_hash() -> &'static str {
+    static DECOY: OnceLock<String> = OnceLock::new();
+    DECOY.get_or_init(|| {
+        let salt = SaltString::encode_b64(&random_bytes::<16>()).expect("16 bytes is a valid salt");
+        Argon2::default()
+            .hash_password(&random_bytes::<32>(), &salt)
+            .expect("Argon2 accepts the default parameters")
+            .to_string()
+    })
+}
+
+/// Checks `password` against the account found for the login email, failing
+/// with [`ApiError::InvalidCredentials`] without telling which part was wrong.
+/// Only once the password matched is a disabled account reported as such.
+pub async fn authenticate(user: Option<User>, password: String) -> Result<User, ApiError> {
+    let hash = match &user {
+        Some(user) => user.password_hash.clone(),
+        None => decoy_hash().to_string(),
+    };
+    let matches = web::block(move || {
+        PasswordHash::new(&hash)
+            .map(|hash| Argon2::default().verify_password(password.as_bytes(), &hash).is_ok())
+            .unwrap_or(false)
+    })
+    .await
+    .map_err(|err| ApiError::Internal(err.to_string()))?;
+
+    match user {
+        Some(user) if matches && user.is_disabled() => Err(ApiError::AccountDisabled),
+        Some(user) if matches => Ok(user),
+        _ => Err(ApiError::InvalidCredentials),
+    }
+}
+
+/// Only this hash of a refresh token or API key is stored, so a leaked database does
+/// not hand out usable tokens.
+pub fn token_hash(token: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(token.as_bytes()))
+}
+
+fn new_refresh_token() -> String {
+    URL_SAFE_NO_PAD.encode(random_bytes::<32>())
+}
+
+fn token_response(
+    data: &AppState,
+    config: &AuthConfig,
+    refresh_token: String,
+    stored: &RefreshToken,
+) -> Result<TokenResponse, ApiError> {
+    let (access_token, _) = config.jwt.issue(stored.user_id, stored.session_id, data.tenant.as_deref())?;
+    Ok(TokenResponse {
+        access_token,
+        token_type: "Bearer",
+        expires_in: config.jwt.access_ttl.num_seconds(),
+        refresh_token,
+        refresh_expires_at: stored.expires_at,
+    })
+}
+
+/// Starts a login session for `user_id` and issues its first tokens.
+pub async fn start_session(data: &AppState, config: &AuthConfig, user_id: ObjectId) -> Result<TokenResponse, ApiError> {
+    let refresh_token = new_refresh_token();
+    let now = Utc::now();
+    let stored = RefreshToken {
+        token_hash: token_hash(&refresh_token),
+        session_id: ObjectId::new(),
+        user_id,
+        created_at: now,
+        expires_at: now + config.refresh_ttl,
+    };
+    data.refresh_tokens.create(&stored).await?;
+    token_response(data, config, refresh_token, &stored)
+}
+
+/// Exchanges a refresh token for a new access token and a new refresh token.
+/// Each refresh token works once; presenting it again revokes its session.
+pub async fn refresh_session(data: &AppState, config: &AuthConfig, refresh_token: &str) -> Result<TokenResponse, ApiError> {
+    let replacement = new_refresh_token();
+    let expires_at = Utc::now() + config.refresh_ttl;
+    let rotation = data.refresh_tokens
+        .rotate(&token_hash(refresh_token), &token_hash(&replacement), expires_at)
+        .await?;
+
+    match rotation {
+        Rotation::Rotated(stored) => match data.users.get(stored.user_id).await? {
+            Some(user) if !user.is_disabled() => token_response(data, config, replacement, &stored),
+            user => {
+                data.refresh_tokens.revoke_session(stored.session_id).await?;
+                Err(if user.is_some() { ApiError::AccountDisabled } else { ApiError::InvalidRefreshToken })
+            }
+        },
+        Rotation::Reused | Rotation::Invalid => Err(ApiError::InvalidRefreshToken),
+    }
+}
+
+/// Ends the session of `user`: its refresh tokens stop working at once, and
+/// the access token used for the request is denylisted until it expires.
+pub async fn end_session(data: &AppState, user: &CurrentUser) -> Result<(), ApiError> {
+    let (session_id, claims) = user.session()?;
+    data.refresh_tokens.revoke_session(session_id).await?;
+    data.revoked_tokens.revoke(&claims.jti, claims.expires_at()).await?;
+    Ok(())
+}
+
+/// Creates an API key for `user_id`, returning it with the key itself, which
+/// is not stored and cannot be shown again.
+pub async fn create_api_key(
+    data: &AppState,
+    user_id: ObjectId,
+    name: String,
+    scopes: Vec<Scope>,
+) -> Result<CreatedApiKey, ApiError> {
+    let key = format!("{}{}", API_KEY_PREFIX, URL_SAFE_NO_PAD.encode(random_bytes::<32>()));
+    let api_key = ApiKey {
+        id: ObjectId::new(),
+        user_id,
+        name,
+        prefix: key.chars().take(API_KEY_PREFIX.len() + 8).collect(),
+        key_hash: token_hash(&key),
+        scopes,
+        created_at: Utc::now(),
+        last_used_at: None,
+    };
+    data.api_keys.create(&api_key).await?;
+    Ok(CreatedApiKey { api_key, key })
+}
+
+/// Looks up the key of an `Authorization: ApiKey` header and records its use.
+async fn resolve_api_key(data: &AppState, key: &str) -> Result<ApiKey, ApiError> {
+    let mut api_key = data.api_keys.find_by_hash(&token_hash(key)).await?.ok_or(ApiError::Unauthorized)?;
+    let now = Utc::now();
+    let stale = api_key.last_used_at.is_none_or(|used| now - used >= Duration::seconds(LAST_USED_RESOLUTION_SECONDS));
+    if stale {
+        data.api_keys.touch(api_key.id, now).await?;
+        api_key.last_used_at = Some(now);
+    }
+    Ok(api_key)
+}
+
+/// The credentials of an `Authorization: <scheme> <credentials>` header.
+fn authorization<'a>(req: &'a HttpRequest, scheme: &str) -> Option<&'a str> {
+    let value = req.headers().get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (given, credentials) = value.split_once(' ')?;
+    given.eq_ignore_ascii_case(scheme).then(|| credentials.trim()).filter(|credentials| !credentials.is_empty())
+}
+
+/// Outcome of verifying the request's access token, kept in its extensions.
+#[derive(Clone)]
+struct VerifiedToken(Option<Claims>);
+
+/// Claims of the request's access token, `None` without a valid one. The
+/// token is verified once per request: the tenant is read from it before the
+/// user can be authenticated against that tenant's storage.
+fn bearer_claims(req: &HttpRequest) -> Option<Claims> {
+    if let Some(VerifiedToken(claims)) = req.extensions().get::<VerifiedToken>() {
+        return claims.clone();
+    }
+    let config = req.app_data::<web::Data<AuthConfig>>().expect("AuthConfig is registered");
+    let claims = authorization(req, "Bearer").and_then(|token| config.jwt.verify(token).ok());
+    req.extensions_mut().insert(VerifiedToken(claims.clone()));
+    claims
+}
+
+/// The `tid` claim of the request's access token, if it carries a valid one.
+pub fn tenant_claim(req: &HttpRequest) -> Option<String> {
+    bearer_claims(req)?.tid
+}
+
+/// How the caller of a request authenticated.
+#[derive(Clone)]
+pub enum Credential {
+    /// A JWT access token from a login session.
+    Session { session_id: ObjectId, claims: Claims },
+    ApiKey { scopes: Vec<Scope> },
+}
+
+/// The caller of a request, identified by the JWT access token in its
+/// `Authorization: Bearer` header or by an `Authorization: ApiKey` header.
+/// Handlers that take it reject requests without valid credentials with 401,
+/// and those of disabled accounts with 403.
+#[derive(Clone)]
+pub struct CurrentUser {
+    pub user: User,
+    pub credential: Credential,
+}
+
+impl CurrentUser {
+    pub fn id(&self) -> ObjectId {
+        self.user.id
+    }
+
+    /// Fails with [`ApiError::Forbidden`] unless the caller has `role`.
+    pub fn require_role(&self, role: Role) -> Result<(), ApiError> {
+        if self.user.role == role {
+            Ok(())
+        } else {
+            Err(ApiError::Forbidden(format!("This endpoint requires the {} role", role.as_str())))
+        }
+    }
+
+    /// Fails with [`ApiError::InsufficientScope`] unless the caller may act
+    /// with `scope`. Login sessions carry every scope.
+    pub fn require_scope(&self, scope: Scope) -> Result<(), ApiError> {
+        match &self.credential {
+            Credential::ApiKey { scopes, .. } if !scopes.contains(&scope) => Err(ApiError::InsufficientScope(scope)),
+            _ => Ok(()),
+        }
+    }
+
+    /// The login session of the caller; API keys cannot manage sessions or
+    /// other keys.
+    pub fn session(&self) -> Result<(ObjectId, &Claims), ApiError> {
+        match &self.credential {
+            Credential::Session { session_id, claims } => Ok((*session_id, claims)),
+            Credential::ApiKey { .. } => {
+                Err(ApiError::Forbidden("This endpoint requires a login session, not an API key".to_string()))
+            }
+        }
+    }
+}
+
+async fn authenticate_request(req: &HttpRequest) -> Result<CurrentUser, ApiError> {
+    let data = TenantState::of(req)?;
+    let (user_id, credential) = resolve_credential(req, &data).await?;
+
+    // Loaded on every request so that disabling an account or changing its
+    // role takes effect at once, not when its access tokens expire.
+    let user = data.users.get(user_id).await?.ok_or(ApiError::Unauthorized)?;
+    if user.is_disabled() {
+        return Err(ApiError::AccountDisabled);
+    }
+    Ok(CurrentUser { user, credential })
+}
+
+async fn resolve_credential(req: &HttpRequest, data: &AppState) -> Result<(ObjectId, Credential), ApiError> {
+    if authorization(req, "Bearer").is_some() {
+        let claims = bearer_claims(req).ok_or(ApiError::Unauthorized)?;
+        let (Ok(user_id), Ok(session_id)) = (ObjectId::parse_str(&claims.sub), ObjectId::parse_str(&claims.sid))
+        else {
+            return Err(ApiError::Unauthorized);
+        };
+        if data.revoked_tokens.is_revoked(&claims.jti).await? {
+            return Err(ApiError::Unauthorized);
+        }
+        return Ok((user_id, Credential::Session { session_id, claims }));
+    }
+
+    let key = authorization(req, "ApiKey").ok_or(ApiError::Unauthorized)?;
+    let api_key = resolve_api_key(data, key).await?;
+    Ok((api_key.user_id, Credential::ApiKey { scopes: api_key.scopes }))
+}
+
+impl FromRequest for CurrentUser {
+    type Error = ApiError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        Box::pin(async move {
+            // Already resolved by a middleware in front of the handler.
+            if let Some(user) = req.extensions().get::<CurrentUser>() {
+                return Ok(user.clone());
+            }
+            let user = authenticate_request(&req).await?;
+            req.extensions_mut().insert(user.clone());
+            Ok(user)
+        })
+    }
+}
+
+/// Middleware in front of the `/tasks` routes: reads need the `tasks:read`
+/// scope and every other method `tasks:write`.
+pub async fn require_task_scope(
+    user: CurrentUser,
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let scope = match *req.method() {
+        Method::GET | Method::HEAD => Scope::TasksRead,
+        _ => Scope::TasksWrite,
+    };
+    user.require_scope(scope)?;
+    next.call(req).await
+}
+
+/// Middleware in front of the `/admin` routes, which only admins may use, and
+/// only from a login session.
+pub async fn require_admin(
+    user: CurrentUser,
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    user.session()?;
+    user.require_role(Role::Admin)?;
+    next.call(req).await
+}
@@ -0,0 +1,199 @@
+use actix_web::http::StatusCode;
+use actix_web::ResponseError;
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::errors::ApiError;
+use crate::lists::Access;
+use crate::models::{ListRole, Task, TaskChanges, TaskInput, TaskPayload};
+use crate::storage::{TaskRepository, TaskScope};
+use crate::{subtasks, validation, AppState};
+
+/// Most operations accepted in one `POST /tasks/bulk` request.
+pub const MAX_BULK_OPERATIONS: usize = 100;
+
+/// Body of `POST /tasks/bulk`. Operations are kept as raw JSON so that a
+/// malformed one fails on its own instead of rejecting the whole batch.
+#[derive(Deserialize)]
+pub struct BulkRequest {
+    pub operations: Vec<Value>,
+}
+
+/// One operation of a bulk request, selected by its `op` member. `revision`
+/// makes an update or delete conditional, as `If-Match` does on `/tasks/{id}`.
+#[derive(Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase", deny_unknown_fields)]
+enum BulkOperation {
+    Create {
+        task: TaskPayload,
+    },
+    /// Full replacement, as `PUT /tasks/{id}`.
+    Update {
+        id: String,
+        task: TaskPayload,
+        revision: Option<i64>,
+    },
+    /// JSON merge patch, as `PATCH /tasks/{id}`.
+    Patch {
+        id: String,
+        patch: Map<String, Value>,
+        revision: Option<i64>,
+    },
+    Delete {
+        id: String,
+        revision: Option<i64>,
+    },
+}
+
+/// A validated operation, ready to run against the repository.
+enum Step {
+    Create(TaskInput),
+    Update { id: ObjectId, changes: TaskChanges, expected_revisions: Option<Vec<i64>> },
+    Delete { id: ObjectId, expected_revisions: Option<Vec<i64>> },
+}
+
+/// Outcome of one operation, carrying the status code the equivalent
+/// single-task request would have answered with.
+#[derive(Serialize)]
+pub struct BulkResult {
+    pub status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task: Option<Task>,
+    /// Problem details, shaped like the body of an error response.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<Value>,
+}
+
+impl BulkResult {
+    fn succeeded(status: StatusCode, task: Option<Task>) -> Self {
+        BulkResult { status: status.as_u16(), task, error: None }
+    }
+
+    fn failed(err: ApiError) -> Self {
+        BulkResult {
+            status: err.status_code().as_u16(),
+            task: None,
+            error: serde_json::to_value(err.problem()).ok(),
+        }
+    }
+}
+
+/// Response body of `DELETE /tasks`.
+#[derive(Serialize)]
+pub struct DeletedTasks {
+    pub deleted: u64,
+}
+
+fn prepare(operation: Value) -> Result<Step, ApiError> {
+    let operation = serde_json::from_value(operation).map_err(|err| ApiError::InvalidRequest(vec![err.to_string()]))?;
+    let step = match operation {
+        BulkOperation::Create { task } => Step::Create(task.validate(true).map_err(ApiError::Validation)?),
+        BulkOperation::Update { id, task, revision } => Step::Update {
+            id: crate::parse_object_id(&id)?,
+            changes: task.validate(false).map_err(ApiError::Validation)?.into(),
+            expected_revisions: revision.map(|revision| vec![revision]),
+        },
+        BulkOperation::Patch { id, patch, revision } => Step::Update {
+            id: crate::parse_object_id(&id)?,
+            changes: validation::merge_patch(patch).map_err(ApiError::Validation)?,
+            expected_revisions: revision.map(|revision| vec![revision]),
+        },
+        BulkOperation::Delete { id, revision } => Step::Delete {
+            id: crate::parse_object_id(&id)?,
+            expected_revisions: revision.map(|revision| vec![revision]),
+        },
+    };
+    Ok(step)
+}
+
+/// Runs the operations in order, returning one result per operation.
+/// Tasks are created as personal tasks of `owner`; updates and deletes only
+/// reach the tasks `access` lets them edit. Consecutive creates are stored
+/// together through [`TaskRepository::create_many`]; a failing operation does
+/// not stop the rest.
+pub async fn execute(data: &AppState, owner: ObjectId, access: &Access, operations: Vec<Value>) -> Vec<BulkResult> {
+    let tasks = data.tasks.as_ref();
+    let scope = &access.scope(ListRole::Editor);
+    let mut results: Vec<Option<BulkResult>> = Vec::with_capacity(operations.len());
+    let mut creates = Vec::new();
+
+    for (index, operation) in operations.into_iter().enumerate() {
+        results.push(None);
+        match prepare(operation) {
+            Ok(Step::Create(input)) if input.parent_id.is_some() => {
+                // The parent may be one of the pending creates.
+                create_pending(tasks, owner, &mut creates, &mut results).await;
+                match subtasks::check_input(tasks, scope, None, &input).await {
+                    Ok(()) => creates.push((index, input)),
+                    Err(err) => results[index] = Some(BulkResult::failed(err)),
+                }
+            }
+            Ok(Step::Create(input)) => creates.push((index, input)),
+            Ok(step) => {
+                create_pending(tasks, owner, &mut creates, &mut results).await;
+                results[index] = Some(run(data, owner, access, scope, step).await);
+            }
+            Err(err) => results[index] = Some(BulkResult::failed(err)),
+        }
+    }
+    create_pending(tasks, owner, &mut creates, &mut results).await;
+
+    results.into_iter().flatten().collect()
+}
+
+/// Stores the queued creates in one batch and records their results.
+async fn create_pending(
+    tasks: &dyn TaskRepository,
+    owner: ObjectId,
+    pending: &mut Vec<(usize, TaskInput)>,
+    results: &mut [Option<BulkResult>],
+) {
+    if pending.is_empty() {
+        return;
+    }
+    let (indexes, inputs): (Vec<usize>, Vec<TaskInput>) = pending.drain(..).unzip();
+
+    let created = tasks.create_many(owner, None, inputs).await;
+    for (index, outcome) in indexes.into_iter().zip(created) {
+        results[index] = Some(match outcome {
+            Ok(task) => BulkResult::succeeded(StatusCode::CREATED, Some(task)),
+            Err(err) => BulkResult::failed(err.into()),
+        });
+    }
+}
+
+async fn run(data: &AppState, owner: ObjectId, access: &Access, scope: &TaskScope, step: Step) -> BulkResult {
+    let tasks = data.tasks.as_ref();
+    let id = match &step {
+        Step::Create(_) => None,
+        Step::Update { id, .. } | Step::Delete { id, .. } => Some(*id),
+    };
+    if let Step::Update { id, changes, .. } = &step {
+        if let Err(err) = subtasks::check_changes(tasks, scope, *id, changes).await {
+            return BulkResult::failed(err);
+        }
+    }
+    let outcome = match step {
+        Step::Create(input) => tasks
+            .create(owner, None, input)
+            .await
+            .map(|task| Some((StatusCode::CREATED, Some(task)))),
+        Step::Update { id, changes, expected_revisions } => tasks
+            .update(scope, id, changes, expected_revisions)
+            .await
+            .map(|task| task.map(|task| (StatusCode::OK, Some(task)))),
+        Step::Delete { id, expected_revisions } => tasks
+            .delete(scope, id, expected_revisions)
+            .await
+            .map(|deleted| deleted.then_some((StatusCode::OK, None))),
+    };
+    match (outcome, id) {
+        (Ok(Some((status, task))), _) => BulkResult::succeeded(status, task),
+        (Ok(None), Some(id)) => match access.write_denied(data, id).await {
+            Ok(err) | Err(err) => BulkResult::failed(err),
+        },
+        (Ok(None), None) => BulkResult::failed(ApiError::NotFound("task")),
+        (Err(err), _) => BulkResult::failed(err.into()),
+    }
+}
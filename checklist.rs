@@ -0,0 +1,154 @@
+use mongodb::bson::oid::ObjectId;
+
+use crate::errors::ApiError;
+use crate::models::{ChecklistItem, ChecklistItemChanges};
+use crate::validation::MAX_CHECKLIST_ITEMS;
+
+/// How often a checklist edit without `If-Match` is retried when another
+/// write to the task gets in between.
+pub const EDIT_ATTEMPTS: usize = 3;
+
+/// Inserts a new, unchecked item at `position`, or last when it is omitted
+/// or past the end.
+pub fn add(items: &mut Vec<ChecklistItem>, text: &str, position: Option<u32>) -> Result<(), ApiError> {
+    if items.len() >= MAX_CHECKLIST_ITEMS {
+        return Err(ApiError::Conflict(format!(
+            "A task can have at most {} checklist items",
+            MAX_CHECKLIST_ITEMS
+        )));
+    }
+    let index = position.map_or(items.len(), |position| (position as usize).min(items.len()));
+    let item = ChecklistItem { id: ObjectId::new().to_hex(), text: text.to_string(), done: false, position: 0 };
+    items.insert(index, item);
+    renumber(items);
+    Ok(())
+}
+
+/// Applies `changes` to the item with id `item_id`. A position past the end
+/// moves the item last.
+pub fn edit(items: &mut Vec<ChecklistItem>, item_id: ObjectId, changes: &ChecklistItemChanges) -> Result<(), ApiError> {
+    let index = find(items, item_id)?;
+    let mut item = items.remove(index);
+    if let Some(text) = &changes.text {
+        item.text = text.clone();
+    }
+    if let Some(done) = changes.done {
+        item.done = done;
+    }
+    let index = changes.position.map_or(index, |position| (position as usize).min(items.len()));
+    items.insert(index, item);
+    renumber(items);
+    Ok(())
+}
+
+pub fn remove(items: &mut Vec<ChecklistItem>, item_id: ObjectId) -> Result<(), ApiError> {
+    let index = find(items, item_id)?;
+    items.remove(index);
+    renumber(items);
+    Ok(())
+}
+
+fn find(items: &[ChecklistItem], item_id: ObjectId) -> Result<usize, ApiError> {
+    let item_id = item_id.to_hex();
+    items.iter().position(|item| item.id == item_id).ok_or(ApiError::NotFound("checklist item"))
+}
+
+/// Numbers the items by their place in the list.
+fn renumber(items: &mut [ChecklistItem]) {
+    for (position, item) in items.iter_mut().enumerate() {
+        item.position = position as u32;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checklist(texts: &[&str]) -> Vec<ChecklistItem> {
+        let mut items = Vec::new();
+        for text in texts {
+            add(&mut items, text, None).unwrap();
+        }
+        items
+    }
+
+    /// Texts in order, each with whether it is checked, after checking that
+    /// the positions follow the order.
+    fn summary(items: &[ChecklistItem]) -> Vec<(&str, bool)> {
+        for (index, item) in items.iter().enumerate() {
+            assert_eq!(item.position as usize, index);
+        }
+        items.iter().map(|item| (item.text.as_str(), item.done)).collect()
+    }
+
+    fn id_of(items: &[ChecklistItem], text: &str) -> ObjectId {
+        let item = items.iter().find(|item| item.text == text).unwrap();
+        ObjectId::parse_str(&item.id).unwrap()
+    }
+
+    #[test]
+    fn add_appends_unchecked_items() {
+        let items = checklist(&["a", "b"]);
+        assert_eq!(summary(&items), [("a", false), ("b", false)]);
+        assert_ne!(items[0].id, items[1].id);
+    }
+
+    #[test]
+    fn add_inserts_at_a_position_and_clamps_past_the_end() {
+        let mut items = checklist(&["a", "b"]);
+        add(&mut items, "first", Some(0)).unwrap();
+        add(&mut items, "last", Some(99)).unwrap();
+        assert_eq!(summary(&items), [("first", false), ("a", false), ("b", false), ("last", false)]);
+    }
+
+    #[test]
+    fn add_refuses_more_than_the_limit() {
+        let mut items = Vec::new();
+        for index in 0..MAX_CHECKLIST_ITEMS {
+            add(&mut items, &index.to_string(), None).unwrap();
+        }
+        assert!(matches!(add(&mut items, "one too many", None), Err(ApiError::Conflict(_))));
+        assert_eq!(items.len(), MAX_CHECKLIST_ITEMS);
+    }
+
+    #[test]
+    fn edit_changes_only_the_given_fields() {
+        let mut items = checklist(&["a", "b"]);
+        let changes = ChecklistItemChanges { done: Some(true), ..ChecklistItemChanges::default() };
+        let b_id = id_of(&items, "b");
+        edit(&mut items, b_id, &changes).unwrap();
+        let changes = ChecklistItemChanges { text: Some("A".to_string()), ..ChecklistItemChanges::default() };
+        let a_id = id_of(&items, "a");
+        edit(&mut items, a_id, &changes).unwrap();
+        assert_eq!(summary(&items), [("A", false), ("b", true)]);
+    }
+
+    #[test]
+    fn edit_moves_items_and_clamps_past_the_end() {
+        let mut items = checklist(&["a", "b", "c"]);
+        let to = |position| ChecklistItemChanges { position: Some(position), ..ChecklistItemChanges::default() };
+        let c_id = id_of(&items, "c");
+        edit(&mut items, c_id, &to(0)).unwrap();
+        assert_eq!(summary(&items), [("c", false), ("a", false), ("b", false)]);
+        edit(&mut items, c_id, &to(99)).unwrap();
+        assert_eq!(summary(&items), [("a", false), ("b", false), ("c", false)]);
+    }
+
+    #[test]
+    fn remove_renumbers_the_rest() {
+        let mut items = checklist(&["a", "b", "c"]);
+        let b_id = id_of(&items, "b");
+        remove(&mut items, b_id).unwrap();
+        assert_eq!(summary(&items), [("a", false), ("c", false)]);
+    }
+
+    #[test]
+    fn unknown_items_are_not_found() {
+        let mut items = checklist(&["a"]);
+        let missing = ObjectId::new();
+        assert!(matches!(remove(&mut items, missing), Err(ApiError::NotFound("checklist item"))));
+        let changes = ChecklistItemChanges::default();
+        assert!(matches!(edit(&mut items, missing, &changes), Err(ApiError::NotFound("checklist item"))));
+        assert_eq!(summary(&items), [("a", false)]);
+    }
+}
@@ -0,0 +1,189 @@
+use std::fmt;
+
+use actix_web::http::header;
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+use serde::Serialize;
+
+use crate::models::Scope;
+use crate::storage::StorageError;
+use crate::validation::FieldErrors;
+
+/// Error returned by the handlers. Every variant is rendered as an RFC 7807
+/// `application/problem+json` body carrying a stable `code` clients can match on.
+#[derive(Debug)]
+pub enum ApiError {
+    /// A path segment that should be a task id is not a valid ObjectId.
+    InvalidId(String),
+    /// Malformed query string or body; one message per problem found.
+    InvalidRequest(Vec<String>),
+    /// Well-formed body with invalid values.
+    Validation(FieldErrors),
+    /// Body larger than the configured limit, in bytes.
+    PayloadTooLarge(usize),
+    /// Missing, expired or unknown bearer token or API key.
+    Unauthorized,
+    /// Wrong email or password on login.
+    InvalidCredentials,
+    /// Unknown, expired, revoked or already used refresh token.
+    InvalidRefreshToken,
+    /// The account was disabled by an admin.
+    AccountDisabled,
+    /// Authenticated, but not allowed to do this.
+    Forbidden(String),
+    /// The API key used lacks the scope the request needs.
+    InsufficientScope(Scope),
+    NotFound(&'static str),
+    Conflict(String),
+    /// `If-Match` named a revision the task is no longer at.
+    PreconditionFailed,
+    /// An `Idempotency-Key` was sent again with a different request body.
+    IdempotencyKeyReused,
+    Database(String),
+    /// Unexpected failure outside of storage.
+    Internal(String),
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum ProblemErrors<'a> {
+    List(&'a [String]),
+    Fields(&'a FieldErrors),
+}
+
+/// The `application/problem+json` body describing an [`ApiError`].
+#[derive(Serialize)]
+pub struct Problem<'a> {
+    title: &'a str,
+    status: u16,
+    code: &'a str,
+    detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    errors: Option<ProblemErrors<'a>>,
+}
+
+impl ApiError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::InvalidId(_) => "invalid_id",
+            ApiError::InvalidRequest(_) => "invalid_request",
+            ApiError::Validation(_) => "validation_failed",
+            ApiError::PayloadTooLarge(_) => "payload_too_large",
+            ApiError::Unauthorized => "unauthorized",
+            ApiError::InvalidCredentials => "invalid_credentials",
+            ApiError::InvalidRefreshToken => "invalid_refresh_token",
+            ApiError::AccountDisabled => "account_disabled",
+            ApiError::Forbidden(_) => "forbidden",
+            ApiError::InsufficientScope(_) => "insufficient_scope",
+            ApiError::NotFound(_) => "not_found",
+            ApiError::Conflict(_) => "conflict",
+            ApiError::PreconditionFailed => "precondition_failed",
+            ApiError::IdempotencyKeyReused => "idempotency_key_reused",
+            ApiError::Database(_) => "database_error",
+            ApiError::Internal(_) => "internal_error",
+        }
+    }
+
+    /// Builds the response body, logging database errors since their cause
+    /// is left out of it.
+    pub fn problem(&self) -> Problem<'_> {
+        match self {
+            ApiError::Database(message) => eprintln!("Database error: {}", message),
+            ApiError::Internal(message) => eprintln!("Internal error: {}", message),
+            _ => {}
+        }
+
+        let status = self.status_code();
+        let errors = match self {
+            ApiError::InvalidRequest(errors) => Some(ProblemErrors::List(errors)),
+            ApiError::Validation(errors) => Some(ProblemErrors::Fields(errors)),
+            _ => None,
+        };
+        Problem {
+            title: status.canonical_reason().unwrap_or("Error"),
+            status: status.as_u16(),
+            code: self.code(),
+            detail: self.detail(),
+            errors,
+        }
+    }
+
+    fn detail(&self) -> String {
+        match self {
+            ApiError::InvalidId(id) => format!("'{}' is not a valid id", id),
+            ApiError::InvalidRequest(errors) => errors.join("; "),
+            ApiError::Validation(_) => "One or more fields are invalid".to_string(),
+            ApiError::PayloadTooLarge(limit) => format!("Request body must not exceed {} bytes", limit),
+            ApiError::Unauthorized => "A valid bearer token or API key is required".to_string(),
+            ApiError::InvalidCredentials => "The email or password is incorrect".to_string(),
+            ApiError::InvalidRefreshToken => "The refresh token is invalid or has expired; log in again".to_string(),
+            ApiError::AccountDisabled => "This account has been disabled".to_string(),
+            ApiError::Forbidden(message) => message.clone(),
+            ApiError::InsufficientScope(scope) => format!("This API key lacks the '{}' scope", scope.as_str()),
+            ApiError::NotFound(resource) => format!("{} not found", resource),
+            ApiError::Conflict(message) => message.clone(),
+            ApiError::PreconditionFailed => {
+                "The task has been modified since it was fetched; reload it and retry".to_string()
+            }
+            ApiError::IdempotencyKeyReused => {
+                "This Idempotency-Key was already used for a request with a different body".to_string()
+            }
+            // The underlying error is logged, not exposed to clients.
+            ApiError::Database(_) => "The request could not be completed due to a storage error".to_string(),
+            ApiError::Internal(_) => "The request could not be completed due to an internal error".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Database(message) | ApiError::Internal(message) => write!(f, "{}: {}", self.code(), message),
+            _ => write!(f, "{}: {}", self.code(), self.detail()),
+        }
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::InvalidId(_) | ApiError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::Validation(_) | ApiError::IdempotencyKeyReused => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            ApiError::Unauthorized | ApiError::InvalidCredentials | ApiError::InvalidRefreshToken => {
+                StatusCode::UNAUTHORIZED
+            }
+            ApiError::AccountDisabled | ApiError::Forbidden(_) | ApiError::InsufficientScope(_) => {
+                StatusCode::FORBIDDEN
+            }
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::PreconditionFailed => StatusCode::PRECONDITION_FAILED,
+            ApiError::Database(_) | ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        response.insert_header((header::CONTENT_TYPE, "application/problem+json"));
+        if let ApiError::Unauthorized = self {
+            response.insert_header((header::WWW_AUTHENTICATE, "Bearer, ApiKey"));
+        }
+        response.json(self.problem())
+    }
+}
+
+impl From<StorageError> for ApiError {
+    fn from(err: StorageError) -> Self {
+        match err {
+            StorageError::Conflict(message) => ApiError::Conflict(message),
+            StorageError::PreconditionFailed => ApiError::PreconditionFailed,
+            StorageError::InvalidParent(message) => {
+                let mut errors = FieldErrors::default();
+                errors.add("parent_id", message);
+                ApiError::Validation(errors)
+            }
+            StorageError::Database(message) => ApiError::Database(message),
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use actix_web::http::header::{self, EntityTag, Header, IfMatch, IfNoneMatch};
+use actix_web::HttpRequest;
+
+use crate::models::Task;
+
+/// Strong ETag of a single task: its revision, which changes on every write.
+pub fn task_etag(task: &Task) -> EntityTag {
+    EntityTag::new_strong(task.revision.to_string())
+}
+
+/// Weak ETag of a task listing, derived from the id, revision and progress of
+/// every task on the page so that any write to one of them or their subtasks,
+/// or a change in which tasks are listed, produces a new tag.
+pub fn list_etag(tasks: &[Task], total: u64, envelope: bool) -> EntityTag {
+    let mut hasher = DefaultHasher::new();
+    for task in tasks {
+        task.id.hash(&mut hasher);
+        task.revision.hash(&mut hasher);
+        task.progress.hash(&mut hasher);
+    }
+    total.hash(&mut hasher);
+    envelope.hash(&mut hasher);
+    EntityTag::new_weak(format!("{:016x}", hasher.finish()))
+}
+
+/// Revisions the request's `If-Match` header allows a write to proceed from,
+/// or `None` when the write is unconditional (no header, or `*`). Tags that
+/// are weak or not one of ours can never match, so they contribute no
+/// revision, and a header that doesn't parse at all matches nothing.
+pub fn expected_revisions(req: &HttpRequest) -> Option<Vec<i64>> {
+    // actix parses an absent header as an empty list, the same as one whose
+    // tags were all dropped as malformed, so presence is checked separately.
+    if !req.headers().contains_key(header::IF_MATCH) {
+        return None;
+    }
+    match IfMatch::parse(req) {
+        Ok(IfMatch::Any) => None,
+        Ok(IfMatch::Items(tags)) => Some(
+            tags.iter()
+                .filter(|tag| !tag.weak)
+                .filter_map(|tag| tag.tag().parse().ok())
+                .collect(),
+        ),
+        Err(_) => Some(Vec::new()),
+    }
+}
+
+/// Whether an `If-None-Match` header matches `etag`, meaning the client's
+/// cached copy is current and a 304 can be sent instead of the body.
+pub fn none_match(if_none_match: Option<&IfNoneMatch>, etag: &EntityTag) -> bool {
+    match if_none_match {
+        Some(IfNoneMatch::Any) => true,
+        Some(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(etag)),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use super::*;
+
+    fn revisions(if_match: Option<&str>) -> Option<Vec<i64>> {
+        let req = match if_match {
+            Some(value) => TestRequest::default().insert_header((header::IF_MATCH, value)),
+            None => TestRequest::default(),
+        };
+        expected_revisions(&req.to_http_request())
+    }
+
+    #[test]
+    fn missing_header_is_unconditional() {
+        assert_eq!(revisions(None), None);
+    }
+
+    #[test]
+    fn wildcard_is_unconditional() {
+        assert_eq!(revisions(Some("*")), None);
+    }
+
+    #[test]
+    fn strong_tags_give_their_revisions() {
+        assert_eq!(revisions(Some("\"3\", \"5\"")), Some(vec![3, 5]));
+    }
+
+    #[test]
+    fn weak_and_foreign_tags_are_ignored() {
+        assert_eq!(revisions(Some("W/\"3\", \"abc\", \"4\"")), Some(vec![4]));
+    }
+
+    #[test]
+    fn unparseable_header_matches_nothing() {
+        assert_eq!(revisions(Some("5")), Some(vec![]));
+        assert_eq!(revisions(Some("W/\"5\"")), Some(vec![]));
+        assert_eq!(revisions(Some("")), Some(vec![]));
+    }
+}
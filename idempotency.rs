@@ -0,0 +1,106 @@
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::http::StatusCode;
+use actix_web::{HttpRequest, HttpResponse};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::errors::ApiError;
+use crate::storage::{IdempotencyRecord, StoredResponse};
+
+pub const IDEMPOTENCY_KEY: &str = "Idempotency-Key";
+
+/// Set on responses replayed from an earlier request with the same key.
+const IDEMPOTENT_REPLAYED: HeaderName = HeaderName::from_static("idempotent-replayed");
+
+/// How long a key is remembered unless `IDEMPOTENCY_TTL_SECONDS` says otherwise.
+pub const DEFAULT_TTL_SECONDS: i64 = 24 * 60 * 60;
+
+pub const MAX_KEY_LENGTH: usize = 255;
+
+/// The `Idempotency-Key` header of the request, if it sent one. Keys are
+/// opaque to the server but must be printable ASCII and reasonably short.
+pub fn key(req: &HttpRequest) -> Result<Option<String>, ApiError> {
+    let Some(value) = req.headers().get(IDEMPOTENCY_KEY) else {
+        return Ok(None);
+    };
+    let key = value.to_str().unwrap_or_default().trim();
+    if key.is_empty() || key.len() > MAX_KEY_LENGTH || !key.chars().all(|c| c.is_ascii_graphic()) {
+        return Err(ApiError::InvalidRequest(vec![format!(
+            "'{}' must be 1 to {} printable ASCII characters",
+            IDEMPOTENCY_KEY, MAX_KEY_LENGTH
+        )]));
+    }
+    Ok(Some(key.to_string()))
+}
+
+/// Fingerprint of a request body. The body is hashed in its parsed form, so
+/// whitespace and member order do not make a retry look like a new request.
+pub fn request_hash(body: &Value) -> String {
+    let canonical = serde_json::to_vec(&sorted_members(body)).unwrap_or_default();
+    URL_SAFE_NO_PAD.encode(Sha256::digest(&canonical))
+}
+
+/// Copy of `value` with the members of every object in key order. serde_json
+/// keeps objects in insertion order here (bson turns on `preserve_order`), so
+/// they must be sorted before serializing to get the same bytes for both.
+fn sorted_members(value: &Value) -> Value {
+    match value {
+        Value::Object(members) => {
+            let mut members: Vec<_> = members.iter().collect();
+            members.sort_by_key(|(name, _)| *name);
+            Value::Object(members.into_iter().map(|(name, value)| (name.clone(), sorted_members(value))).collect())
+        }
+        Value::Array(items) => Value::Array(items.iter().map(sorted_members).collect()),
+        other => other.clone(),
+    }
+}
+
+/// Answers a request whose key was already claimed by an earlier one.
+pub fn replay(record: IdempotencyRecord, request_hash: &str) -> Result<HttpResponse, ApiError> {
+    if record.request_hash != request_hash {
+        return Err(ApiError::IdempotencyKeyReused);
+    }
+    match record.response {
+        Some(response) => {
+            let mut response = response.to_response();
+            response.headers_mut().insert(IDEMPOTENT_REPLAYED, HeaderValue::from_static("true"));
+            Ok(response)
+        }
+        None => Err(ApiError::Conflict(
+            "A request with this Idempotency-Key is still being processed".to_string(),
+        )),
+    }
+}
+
+impl StoredResponse {
+    pub fn to_response(&self) -> HttpResponse {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::OK);
+        let mut response = HttpResponse::build(status);
+        for header in &self.headers {
+            response.insert_header(header.clone());
+        }
+        response.content_type("application/json").body(self.body.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn member_order_does_not_change_the_hash() {
+        let body: Value = serde_json::from_str(r#"{"title":"a","tags":["x"],"due":{"at":1,"tz":"UTC"}}"#).unwrap();
+        let reordered: Value = serde_json::from_str(r#"{"due":{"tz":"UTC","at":1},"tags":["x"],"title":"a"}"#).unwrap();
+        assert_eq!(request_hash(&body), request_hash(&reordered));
+    }
+
+    #[test]
+    fn different_bodies_hash_differently() {
+        assert_ne!(request_hash(&json!({ "title": "a" })), request_hash(&json!({ "title": "b" })));
+        assert_ne!(request_hash(&json!({ "tags": ["a", "b"] })), request_hash(&json!({ "tags": ["b", "a"] })));
+    }
+}
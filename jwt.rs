@@ -0,0 +1,146 @@
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::ApiError;
+
+pub const DEFAULT_ISSUER: &str = "rust_backend";
+pub const DEFAULT_ACCESS_TTL_SECONDS: i64 = 15 * 60;
+pub const DEFAULT_LEEWAY_SECONDS: u64 = 60;
+
+/// Shortest accepted `JWT_SECRET`, matching the size of an HS256 key.
+const MIN_SECRET_LENGTH: usize = 32;
+
+/// Claims of an access token.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Claims {
+    /// Id of the user.
+    pub sub: String,
+    /// Login session the token was issued in, see
+    /// [`crate::storage::RefreshToken`].
+    pub sid: String,
+    /// Unique token id, used to revoke the token before it expires.
+    pub jti: String,
+    /// Tenant the user belongs to, when the server hosts several.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tid: Option<String>,
+    pub iss: String,
+    pub aud: String,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+impl Claims {
+    pub fn expires_at(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp(self.exp, 0).unwrap_or_default()
+    }
+}
+
+/// Signs and verifies access tokens.
+pub struct JwtSettings {
+    algorithm: Algorithm,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    issuer: String,
+    audience: String,
+    pub access_ttl: Duration,
+    /// Clock skew tolerated when checking `exp`, in seconds.
+    leeway: u64,
+}
+
+fn env_or(name: &str, default: &str) -> String {
+    std::env::var(name).unwrap_or_else(|_| default.to_string())
+}
+
+fn read_key_file(variable: &str) -> Vec<u8> {
+    let path = std::env::var(variable).unwrap_or_else(|_| panic!("{} must be set for RS256", variable));
+    std::fs::read(&path).unwrap_or_else(|err| panic!("Failed to read {} '{}': {}", variable, path, err))
+}
+
+impl JwtSettings {
+    /// Reads the settings from the environment, panicking on invalid values:
+    ///
+    /// - `JWT_ALGORITHM`: `HS256` (default) or `RS256`
+    /// - `JWT_SECRET`: shared secret for HS256, at least 32 bytes
+    /// - `JWT_PRIVATE_KEY_FILE`, `JWT_PUBLIC_KEY_FILE`: PEM key pair for RS256
+    /// - `JWT_ISSUER`, `JWT_AUDIENCE`: both default to `rust_backend`
+    /// - `JWT_ACCESS_TTL_SECONDS`: access token lifetime, default 15 minutes
+    /// - `JWT_LEEWAY_SECONDS`: tolerated clock skew, default 60
+    pub fn from_env() -> Self {
+        let (algorithm, encoding_key, decoding_key) = match env_or("JWT_ALGORITHM", "HS256").as_str() {
+            "HS256" => {
+                let secret = std::env::var("JWT_SECRET").expect("JWT_SECRET must be set for HS256");
+                if secret.len() < MIN_SECRET_LENGTH {
+                    panic!("JWT_SECRET must be at least {} bytes long", MIN_SECRET_LENGTH);
+                }
+                (
+                    Algorithm::HS256,
+                    EncodingKey::from_secret(secret.as_bytes()),
+                    DecodingKey::from_secret(secret.as_bytes()),
+                )
+            }
+            "RS256" => (
+                Algorithm::RS256,
+                EncodingKey::from_rsa_pem(&read_key_file("JWT_PRIVATE_KEY_FILE"))
+                    .expect("JWT_PRIVATE_KEY_FILE must hold an RSA private key in PEM format"),
+                DecodingKey::from_rsa_pem(&read_key_file("JWT_PUBLIC_KEY_FILE"))
+                    .expect("JWT_PUBLIC_KEY_FILE must hold an RSA public key in PEM format"),
+            ),
+            other => panic!("Unknown JWT_ALGORITHM '{}', expected 'HS256' or 'RS256'", other),
+        };
+
+        let access_ttl = env_or("JWT_ACCESS_TTL_SECONDS", &DEFAULT_ACCESS_TTL_SECONDS.to_string())
+            .parse::<i64>()
+            .expect("JWT_ACCESS_TTL_SECONDS must be a number of seconds");
+        let leeway = env_or("JWT_LEEWAY_SECONDS", &DEFAULT_LEEWAY_SECONDS.to_string())
+            .parse::<u64>()
+            .expect("JWT_LEEWAY_SECONDS must be a number of seconds");
+
+        JwtSettings {
+            algorithm,
+            encoding_key,
+            decoding_key,
+            issuer: env_or("JWT_ISSUER", DEFAULT_ISSUER),
+            audience: env_or("JWT_AUDIENCE", DEFAULT_ISSUER),
+            access_ttl: Duration::seconds(access_ttl),
+            leeway,
+        }
+    }
+
+    /// Signs a new access token for `user_id` of `tenant`.
+    pub fn issue(
+        &self,
+        user_id: ObjectId,
+        session_id: ObjectId,
+        tenant: Option<&str>,
+    ) -> Result<(String, Claims), ApiError> {
+        let now = Utc::now();
+        let claims = Claims {
+            sub: user_id.to_hex(),
+            sid: session_id.to_hex(),
+            jti: ObjectId::new().to_hex(),
+            tid: tenant.map(str::to_string),
+            iss: self.issuer.clone(),
+            aud: self.audience.clone(),
+            iat: now.timestamp(),
+            exp: (now + self.access_ttl).timestamp(),
+        };
+        let token = jsonwebtoken::encode(&Header::new(self.algorithm), &claims, &self.encoding_key)
+            .map_err(|err| ApiError::Internal(err.to_string()))?;
+        Ok((token, claims))
+    }
+
+    /// Checks the signature, issuer, audience and expiry of an access token.
+    pub fn verify(&self, token: &str) -> Result<Claims, ApiError> {
+        let mut validation = Validation::new(self.algorithm);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_audience(&[&self.audience]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+        validation.leeway = self.leeway;
+
+        jsonwebtoken::decode::<Claims>(token, &self.decoding_key, &validation)
+            .map(|data| data.claims)
+            .map_err(|_| ApiError::Unauthorized)
+    }
+}
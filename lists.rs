@@ -0,0 +1,55 @@
+use mongodb::bson::oid::ObjectId;
+
+use crate::errors::ApiError;
+use crate::models::{ListRole, TaskList};
+use crate::storage::TaskScope;
+use crate::AppState;
+
+/// The lists a user is a member of, loaded once per request to decide which
+/// tasks the request may see.
+pub struct Access {
+    user_id: ObjectId,
+    lists: Vec<TaskList>,
+}
+
+impl Access {
+    pub async fn load(data: &AppState, user_id: ObjectId) -> Result<Self, ApiError> {
+        let lists = data.lists.for_member(user_id).await?;
+        Ok(Access { user_id, lists })
+    }
+
+    /// The user's personal tasks plus the tasks of every list in which they
+    /// have at least the `min` role.
+    pub fn scope(&self, min: ListRole) -> TaskScope {
+        let lists = self
+            .lists
+            .iter()
+            .filter(|list| list.role_of(&self.user_id).is_some_and(|role| role >= min))
+            .map(|list| list.id)
+            .collect();
+        TaskScope { owner: Some(self.user_id), lists, unowned: false }
+    }
+
+    /// The error for a write to task `id` that matched nothing in the editor
+    /// scope: viewers of the task's list are told they may not change it,
+    /// everyone else that it does not exist.
+    pub async fn write_denied(&self, data: &AppState, id: ObjectId) -> Result<ApiError, ApiError> {
+        if data.tasks.get(&self.scope(ListRole::Viewer), id).await?.is_some() {
+            return Ok(ApiError::Forbidden("Viewers of a list cannot change its tasks".to_string()));
+        }
+        Ok(ApiError::NotFound("task"))
+    }
+}
+
+/// Loads list `id` for a member with at least the `min` role. Lists the
+/// user is not a member of are reported as missing.
+pub async fn require(data: &AppState, user_id: ObjectId, id: ObjectId, min: ListRole) -> Result<TaskList, ApiError> {
+    let list = data.lists.get(id).await?.ok_or(ApiError::NotFound("list"))?;
+    match list.role_of(&user_id) {
+        None => Err(ApiError::NotFound("list")),
+        Some(role) if role < min => {
+            Err(ApiError::Forbidden(format!("This requires the {} role on the list", min.as_str())))
+        }
+        Some(_) => Ok(list),
+    }
+}
@@ -1,114 +1,992 @@
-use actix_web::{web, App, HttpServer, Responder, HttpResponse};
+use actix_web::error::JsonPayloadError;
+use actix_web::http::header::{self, ETag, IfNoneMatch};
+use actix_web::middleware::from_fn;
+use actix_web::{web, App, HttpRequest, HttpServer, Responder, HttpResponse};
 use actix_cors::Cors;
-use serde::{Serialize, Deserialize};
-use mongodb::{Client, Collection};
-use mongodb::bson::doc;
-use futures::stream::StreamExt;
-
-#[derive(Serialize, Deserialize, Clone)]
-struct Task {
-    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
-    id: Option<String>,
-    title: String,
-}
-
-struct AppState {
-    tasks_collection: Collection<mongodb::bson::Document>,
-}
-
-async fn get_tasks(data: web::Data<AppState>) -> impl Responder {
-    let mut cursor = data.tasks_collection.find(doc! {}).await.unwrap();
-    let mut tasks = Vec::new();
-    
-    while let Some(result) = cursor.next().await {
-        match result {
-            Ok(doc) => {
-                if let (Some(id), Some(title)) = (doc.get_object_id("_id").ok(), doc.get_str("title").ok()) {
-                    tasks.push(serde_json::json!({
-                        "_id": id.to_hex(),
-                        "title": title
-                    }));
-                }
+use chrono::{Duration, Utc};
+use mongodb::Client;
+use mongodb::bson::oid::ObjectId;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+mod admin;
+mod auth;
+mod bulk;
+mod checklist;
+mod errors;
+mod etag;
+mod idempotency;
+mod jwt;
+mod lists;
+mod models;
+mod pagination;
+mod query;
+mod search;
+mod storage;
+mod subtasks;
+mod tenant;
+mod validation;
+
+use admin::{TransferRequest, UserListParams, UserPageResponse};
+use auth::{AuthConfig, CurrentUser, LoginResponse, RefreshRequest};
+use bulk::{BulkRequest, DeletedTasks, MAX_BULK_OPERATIONS};
+use errors::ApiError;
+use jwt::JwtSettings;
+use lists::Access;
+use models::{
+    ApiKeyPayload, ChecklistItem, ChecklistItemChanges, ChecklistItemPayload, Credentials, ListMember, ListPayload,
+    ListRole, MemberPayload, RetaggedTasks, Role, TagMergePayload, TagRenamePayload, TagsPayload, TaskChanges,
+    TaskList, TaskPayload, User,
+};
+use pagination::{TaskPageResponse, MAX_PAGE_SIZE};
+use query::ListParams;
+use search::{SearchParams, SearchResult};
+use storage::{
+    tenant_database, ApiKeyRepository, IdempotencyRepository, ListRepository, MemoryApiKeyRepository,
+    MemoryIdempotencyRepository, MemoryListRepository, MemoryRefreshTokenRepository, MemoryRevokedTokenRepository,
+    MemoryTaskRepository, MemoryUserRepository, MongoApiKeyRepository, MongoIdempotencyRepository,
+    MongoListRepository, MongoRefreshTokenRepository, MongoRevokedTokenRepository, MongoTaskRepository,
+    MongoUserRepository, RefreshTokenRepository, RevokedTokenRepository, SqliteApiKeyRepository, SqliteDatabase,
+    SqliteIdempotencyRepository, SqliteListRepository, SqliteRefreshTokenRepository, SqliteRevokedTokenRepository,
+    SqliteTaskRepository, SqliteUserRepository, StorageError, StoredResponse, TaskRepository, TaskScope,
+    TenantCollection, UserRepository,
+};
+use tenant::{TenantState, TenantStrategy, Tenants};
+use validation::FieldErrors;
+
+/// Name of the MongoDB database, and prefix of per-tenant databases.
+const DATABASE_NAME: &str = "rust_backend";
+
+/// Total number of matching tasks, sent with bare-array listings.
+const TOTAL_COUNT: &str = "X-Total-Count";
+
+/// Maximum size of a JSON request body unless `JSON_BODY_LIMIT` says otherwise.
+const DEFAULT_JSON_BODY_LIMIT: usize = 64 * 1024;
+
+/// The storage of one tenant, see [`Tenants`].
+pub struct AppState {
+    /// Id of the tenant, `None` when the server hosts a single organization.
+    tenant: Option<String>,
+    tasks: Arc<dyn TaskRepository>,
+    idempotency: Arc<dyn IdempotencyRepository>,
+    users: Arc<dyn UserRepository>,
+    refresh_tokens: Arc<dyn RefreshTokenRepository>,
+    revoked_tokens: Arc<dyn RevokedTokenRepository>,
+    api_keys: Arc<dyn ApiKeyRepository>,
+    lists: Arc<dyn ListRepository>,
+}
+
+fn parse_object_id(id: &str) -> Result<ObjectId, ApiError> {
+    ObjectId::parse_str(id).map_err(|_| ApiError::InvalidId(id.to_string()))
+}
+
+async fn get_tasks(
+    data: TenantState,
+    user: CurrentUser,
+    req: HttpRequest,
+    params: web::Query<Vec<(String, String)>>,
+    if_none_match: Option<web::Header<IfNoneMatch>>,
+) -> Result<HttpResponse, ApiError> {
+    let scope = Access::load(&data, user.id()).await?.scope(ListRole::Viewer);
+    list_tasks(&data, &scope, &req, &params, if_none_match.as_deref()).await
+}
+
+/// Lists the tasks in `scope`, shared by `GET /tasks`, `GET /lists/{id}/tasks`
+/// and the admin views of another user's tasks and of unowned ones.
+async fn list_tasks(
+    data: &AppState,
+    scope: &TaskScope,
+    req: &HttpRequest,
+    params: &[(String, String)],
+    if_none_match: Option<&IfNoneMatch>,
+) -> Result<HttpResponse, ApiError> {
+    let ListParams { query, envelope } = ListParams::parse(params).map_err(ApiError::InvalidRequest)?;
+    let mut page = data.tasks.list(scope, &query).await?;
+    subtasks::fill_progress(data.tasks.as_ref(), &mut page.tasks).await?;
+    
+    let etag = etag::list_etag(&page.tasks, page.total, envelope);
+    if etag::none_match(if_none_match, &etag) {
+        return Ok(HttpResponse::NotModified().insert_header(ETag(etag)).finish());
+    }
+    
+    let mut response = HttpResponse::Ok();
+    response.insert_header(ETag(etag));
+    if envelope {
+        return Ok(response.json(TaskPageResponse::new(page, &query)));
+    }
+    // Old clients expecting every task in the array can tell from the headers
+    // that it is only the first page.
+    response.insert_header((TOTAL_COUNT, page.total.to_string()));
+    if page.has_more {
+        let link = pagination::next_page_link(req.path(), req.query_string(), &query, page.tasks.len());
+        response.insert_header((header::LINK, link));
+    }
+    Ok(response.json(page.tasks))
+}
+
+async fn search_tasks(
+    data: TenantState,
+    user: CurrentUser,
+    params: web::Query<SearchParams>,
+) -> Result<HttpResponse, ApiError> {
+    let terms = search::tokenize(&params.q);
+    if terms.is_empty() {
+        return Err(ApiError::InvalidRequest(vec!["'q' must contain at least one word".to_string()]));
+    }
+    let limit = params.limit.unwrap_or(MAX_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    
+    let scope = Access::load(&data, user.id()).await?.scope(ListRole::Viewer);
+    let hits = data.tasks.search(&scope, &terms, limit).await?;
+    let results: Vec<SearchResult> = hits.into_iter().map(|hit| SearchResult::new(hit, &terms)).collect();
+    Ok(HttpResponse::Ok().json(results))
+}
+
+async fn get_task(
+    data: TenantState,
+    user: CurrentUser,
+    path: web::Path<String>,
+    if_none_match: Option<web::Header<IfNoneMatch>>,
+) -> Result<HttpResponse, ApiError> {
+    let object_id = parse_object_id(&path)?;
+    let scope = Access::load(&data, user.id()).await?.scope(ListRole::Viewer);
+    let task = data.tasks.get(&scope, object_id).await?.ok_or(ApiError::NotFound("task"))?;
+    
+    let etag = etag::task_etag(&task);
+    if etag::none_match(if_none_match.as_deref(), &etag) {
+        return Ok(HttpResponse::NotModified().insert_header(ETag(etag)).finish());
+    }
+    Ok(HttpResponse::Ok().insert_header(ETag(etag)).json(task))
+}
+
+async fn add_task(
+    data: TenantState,
+    user: CurrentUser,
+    req: HttpRequest,
+    body: web::Json<serde_json::Value>,
+) -> Result<HttpResponse, ApiError> {
+    add_task_to(&data, &user, None, &req, body.into_inner()).await
+}
+
+/// Creates a task in `list`, or a personal one. With an `Idempotency-Key`, a
+/// retry of a request that already succeeded gets the original response
+/// instead of a second task.
+async fn add_task_to(
+    data: &AppState,
+    user: &CurrentUser,
+    list: Option<ObjectId>,
+    req: &HttpRequest,
+    body: serde_json::Value,
+) -> Result<HttpResponse, ApiError> {
+    let Some(key) = idempotency::key(req)? else {
+        return Ok(create_task(data, user, list, body).await?.to_response());
+    };
+    
+    // Keys are chosen by clients, so they only need to be unique per user
+    // and per list.
+    let key = match list {
+        Some(list) => format!("{}:{}:{}", user.id(), list, key),
+        None => format!("{}:{}", user.id(), key),
+    };
+    let request_hash = idempotency::request_hash(&body);
+    if let Some(record) = data.idempotency.claim(&key, &request_hash).await? {
+        return idempotency::replay(record, &request_hash);
+    }
+    
+    match create_task(data, user, list, body).await {
+        Ok(response) => {
+            // The task exists at this point, so report success even if the
+            // response could not be recorded; retries then see the key as
+            // still in progress rather than creating a duplicate.
+            if let Err(err) = data.idempotency.complete(&key, &response).await {
+                eprintln!("Failed to record idempotent response: {}", err);
             }
-            Err(_) => {}
+            Ok(response.to_response())
+        }
+        Err(err) => {
+            data.idempotency.release(&key).await?;
+            Err(err)
         }
     }
+}
+
+async fn create_task(
+    data: &AppState,
+    user: &CurrentUser,
+    list: Option<ObjectId>,
+    body: serde_json::Value,
+) -> Result<StoredResponse, ApiError> {
+    let payload: TaskPayload =
+        serde_json::from_value(body).map_err(|err| ApiError::InvalidRequest(vec![err.to_string()]))?;
+    let input = payload.validate(true).map_err(ApiError::Validation)?;
+    let scope = Access::load(data, user.id()).await?.scope(ListRole::Editor);
+    subtasks::check_input(data.tasks.as_ref(), &scope, list, &input).await?;
+    let task = data.tasks.create(user.id(), list, input).await?;
+    
+    let headers = vec![
+        (header::LOCATION.to_string(), format!("/tasks/{}", task.id.as_deref().unwrap_or_default())),
+        (header::ETAG.to_string(), etag::task_etag(&task).to_string()),
+    ];
+    let body = serde_json::to_string(&task).map_err(|err| ApiError::Database(err.to_string()))?;
+    Ok(StoredResponse { status: 201, headers, body })
+}
+
+/// Shared by every handler that edits a task: applies `changes`, honoring
+/// `If-Match`, and responds with the updated task and its new ETag. Tasks in
+/// shared lists need the editor role.
+async fn write_task(
+    data: &AppState,
+    user: &CurrentUser,
+    id: &str,
+    changes: TaskChanges,
+    req: &HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let object_id = parse_object_id(id)?;
+    let expected_revisions = etag::expected_revisions(req);
+    let access = Access::load(data, user.id()).await?;
+    let scope = access.scope(ListRole::Editor);
+    subtasks::check_changes(data.tasks.as_ref(), &scope, object_id, &changes).await?;
     
-    HttpResponse::Ok().json(tasks)
+    match data.tasks.update(&scope, object_id, changes, expected_revisions).await? {
+        Some(task) => Ok(HttpResponse::Ok().insert_header(ETag(etag::task_etag(&task))).json(task)),
+        None => Err(access.write_denied(data, object_id).await?),
+    }
 }
 
-async fn add_task(data: web::Data<AppState>, task: web::Json<Task>) -> impl Responder {
-    let new_task = doc! {
-        "title": &task.title
+/// Shared by the checklist handlers: applies `edit` to the task's checklist
+/// and responds like [`write_task`]. Without `If-Match`, an edit that lost a
+/// race with another write is redone on the fresh checklist rather than
+/// overwriting it.
+async fn edit_checklist<F>(
+    data: &AppState,
+    user: &CurrentUser,
+    id: &str,
+    req: &HttpRequest,
+    edit: F,
+) -> Result<HttpResponse, ApiError>
+where
+    F: Fn(&mut Vec<ChecklistItem>) -> Result<(), ApiError>,
+{
+    let object_id = parse_object_id(id)?;
+    let expected_revisions = etag::expected_revisions(req);
+    let access = Access::load(data, user.id()).await?;
+    let scope = access.scope(ListRole::Editor);
+    
+    for _ in 0..checklist::EDIT_ATTEMPTS {
+        let Some(task) = data.tasks.get(&scope, object_id).await? else {
+            return Err(access.write_denied(data, object_id).await?);
+        };
+        let mut items = task.checklist;
+        edit(&mut items)?;
+        
+        let changes = TaskChanges { checklist: Some(items), ..TaskChanges::default() };
+        let expected = expected_revisions.clone().unwrap_or_else(|| vec![task.revision]);
+        match data.tasks.update(&scope, object_id, changes, Some(expected)).await {
+            Ok(Some(task)) => return Ok(HttpResponse::Ok().insert_header(ETag(etag::task_etag(&task))).json(task)),
+            Ok(None) => return Err(access.write_denied(data, object_id).await?),
+            Err(StorageError::PreconditionFailed) if expected_revisions.is_none() => continue,
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Err(ApiError::Conflict("The task kept changing while editing its checklist; retry the request".to_string()))
+}
+
+async fn update_task(
+    data: TenantState,
+    user: CurrentUser,
+    path: web::Path<String>,
+    task: web::Json<TaskPayload>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let input = task.into_inner().validate(false).map_err(ApiError::Validation)?;
+    write_task(&data, &user, &path, input.into(), &req).await
+}
+
+async fn patch_task(
+    data: TenantState,
+    user: CurrentUser,
+    path: web::Path<String>,
+    patch: web::Json<serde_json::Map<String, serde_json::Value>>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let changes = validation::merge_patch(patch.into_inner()).map_err(ApiError::Validation)?;
+    write_task(&data, &user, &path, changes, &req).await
+}
+
+async fn complete_task(
+    data: TenantState,
+    user: CurrentUser,
+    path: web::Path<String>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    write_task(&data, &user, &path, TaskChanges::completion(true), &req).await
+}
+
+async fn reopen_task(
+    data: TenantState,
+    user: CurrentUser,
+    path: web::Path<String>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    write_task(&data, &user, &path, TaskChanges::completion(false), &req).await
+}
+
+async fn delete_task(
+    data: TenantState,
+    user: CurrentUser,
+    path: web::Path<String>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let object_id = parse_object_id(&path)?;
+    let expected_revisions = etag::expected_revisions(&req);
+    let access = Access::load(&data, user.id()).await?;
+    
+    if data.tasks.delete(&access.scope(ListRole::Editor), object_id, expected_revisions).await? {
+        Ok(HttpResponse::Ok().finish())
+    } else {
+        Err(access.write_denied(&data, object_id).await?)
+    }
+}
+
+/// Adds tags to a task, leaving the ones it already carries alone.
+async fn add_task_tags(
+    data: TenantState,
+    user: CurrentUser,
+    path: web::Path<String>,
+    body: web::Json<TagsPayload>,
+) -> Result<HttpResponse, ApiError> {
+    let object_id = parse_object_id(&path)?;
+    let tags = body.into_inner().validate().map_err(ApiError::Validation)?;
+    let access = Access::load(&data, user.id()).await?;
+    
+    match data.tasks.add_tags(&access.scope(ListRole::Editor), object_id, &tags).await? {
+        Some(task) => Ok(HttpResponse::Ok().insert_header(ETag(etag::task_etag(&task))).json(task)),
+        None => Err(access.write_denied(&data, object_id).await?),
+    }
+}
+
+async fn remove_task_tag(
+    data: TenantState,
+    user: CurrentUser,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, ApiError> {
+    let (id, tag) = path.into_inner();
+    let object_id = parse_object_id(&id)?;
+    let tag = validation::tag_value(&tag)
+        .map_err(|message| ApiError::InvalidRequest(vec![format!("tag '{}' {}", tag, message)]))?;
+    let access = Access::load(&data, user.id()).await?;
+    
+    match data.tasks.remove_tags(&access.scope(ListRole::Editor), object_id, &[tag]).await? {
+        Some(task) => Ok(HttpResponse::Ok().insert_header(ETag(etag::task_etag(&task))).json(task)),
+        None => Err(access.write_denied(&data, object_id).await?),
+    }
+}
+
+async fn add_checklist_item(
+    data: TenantState,
+    user: CurrentUser,
+    path: web::Path<String>,
+    body: web::Json<ChecklistItemPayload>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let (text, position) = body.into_inner().validate().map_err(ApiError::Validation)?;
+    edit_checklist(&data, &user, &path, &req, |items| checklist::add(items, &text, position)).await
+}
+
+/// Edits, checks or moves a checklist item, given a JSON merge patch of it.
+async fn patch_checklist_item(
+    data: TenantState,
+    user: CurrentUser,
+    path: web::Path<(String, String)>,
+    patch: web::Json<serde_json::Map<String, serde_json::Value>>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let (id, item_id) = path.into_inner();
+    let item_id = parse_object_id(&item_id)?;
+    let changes = validation::checklist_item_patch(patch.into_inner()).map_err(ApiError::Validation)?;
+    edit_checklist(&data, &user, &id, &req, |items| checklist::edit(items, item_id, &changes)).await
+}
+
+async fn check_checklist_item(
+    data: TenantState,
+    user: CurrentUser,
+    path: web::Path<(String, String)>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let (id, item_id) = path.into_inner();
+    let item_id = parse_object_id(&item_id)?;
+    let changes = ChecklistItemChanges { done: Some(true), ..ChecklistItemChanges::default() };
+    edit_checklist(&data, &user, &id, &req, |items| checklist::edit(items, item_id, &changes)).await
+}
+
+async fn uncheck_checklist_item(
+    data: TenantState,
+    user: CurrentUser,
+    path: web::Path<(String, String)>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let (id, item_id) = path.into_inner();
+    let item_id = parse_object_id(&item_id)?;
+    let changes = ChecklistItemChanges { done: Some(false), ..ChecklistItemChanges::default() };
+    edit_checklist(&data, &user, &id, &req, |items| checklist::edit(items, item_id, &changes)).await
+}
+
+async fn remove_checklist_item(
+    data: TenantState,
+    user: CurrentUser,
+    path: web::Path<(String, String)>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let (id, item_id) = path.into_inner();
+    let item_id = parse_object_id(&item_id)?;
+    edit_checklist(&data, &user, &id, &req, |items| checklist::remove(items, item_id)).await
+}
+
+async fn bulk_tasks(
+    data: TenantState,
+    user: CurrentUser,
+    request: web::Json<BulkRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let operations = request.into_inner().operations;
+    if operations.len() > MAX_BULK_OPERATIONS {
+        return Err(ApiError::InvalidRequest(vec![format!(
+            "at most {} operations are allowed per request",
+            MAX_BULK_OPERATIONS
+        )]));
+    }
+    
+    let access = Access::load(&data, user.id()).await?;
+    let results = bulk::execute(&data, user.id(), &access, operations).await;
+    Ok(HttpResponse::Ok().json(results))
+}
+
+async fn delete_tasks(
+    data: TenantState,
+    user: CurrentUser,
+    params: web::Query<Vec<(String, String)>>,
+) -> Result<HttpResponse, ApiError> {
+    let filter = query::parse_filter(&params).map_err(ApiError::InvalidRequest)?;
+    let scope = Access::load(&data, user.id()).await?.scope(ListRole::Editor);
+    let deleted = data.tasks.delete_matching(&scope, &filter).await?;
+    Ok(HttpResponse::Ok().json(DeletedTasks { deleted }))
+}
+
+async fn register(
+    data: TenantState,
+    config: web::Data<AuthConfig>,
+    body: web::Json<Credentials>,
+) -> Result<HttpResponse, ApiError> {
+    let (email, password) = body.into_inner().validate().map_err(ApiError::Validation)?;
+    let user = User {
+        id: ObjectId::new(),
+        role: config.initial_role(&email),
+        email,
+        password_hash: auth::hash_password(password).await?,
+        disabled_at: None,
+        created_at: Utc::now(),
     };
     
-    match data.tasks_collection.insert_one(new_task).await {
-        Ok(_) => HttpResponse::Created().finish(),
-        Err(_) => HttpResponse::InternalServerError().finish(),
+    match data.users.create(&user).await {
+        Ok(()) => Ok(HttpResponse::Created().json(user)),
+        Err(StorageError::Conflict(_)) => {
+            Err(ApiError::Conflict("An account with this email already exists".to_string()))
+        }
+        Err(err) => Err(err.into()),
     }
 }
 
-async fn update_task(data: web::Data<AppState>, path: web::Path<String>, task: web::Json<Task>) -> impl Responder {
-    let id = path.into_inner();
-    let object_id = match mongodb::bson::oid::ObjectId::parse_str(&id) {
-        Ok(oid) => oid,
-        Err(_) => return HttpResponse::BadRequest().body("Invalid ID"),
+async fn login(
+    data: TenantState,
+    config: web::Data<AuthConfig>,
+    body: web::Json<Credentials>,
+) -> Result<HttpResponse, ApiError> {
+    let Credentials { email, password } = body.into_inner();
+    let (Some(email), Some(password)) = (email, password) else {
+        return Err(ApiError::InvalidRequest(vec!["'email' and 'password' are required".to_string()]));
     };
     
-    let filter = doc! { "_id": object_id };
-    let update = doc! { "$set": { "title": &task.title } };
+    let user = data.users.find_by_email(&email.trim().to_lowercase()).await?;
+    let user = auth::authenticate(user, password).await?;
+    let tokens = auth::start_session(&data, &config, user.id).await?;
     
-    match data.tasks_collection.update_one(filter, update).await {
-        Ok(result) => {
-            if result.matched_count > 0 {
-                HttpResponse::Ok().finish()
-            } else {
-                HttpResponse::NotFound().finish()
-            }
-        }
-        Err(_) => HttpResponse::InternalServerError().finish(),
+    Ok(HttpResponse::Ok().json(LoginResponse { tokens, user }))
+}
+
+async fn refresh(
+    data: TenantState,
+    config: web::Data<AuthConfig>,
+    body: web::Json<RefreshRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let tokens = auth::refresh_session(&data, &config, &body.refresh_token).await?;
+    Ok(HttpResponse::Ok().json(tokens))
+}
+
+async fn logout(data: TenantState, user: CurrentUser) -> Result<HttpResponse, ApiError> {
+    auth::end_session(&data, &user).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+async fn current_user(user: CurrentUser) -> HttpResponse {
+    HttpResponse::Ok().json(user.user)
+}
+
+async fn create_api_key(
+    data: TenantState,
+    user: CurrentUser,
+    body: web::Json<ApiKeyPayload>,
+) -> Result<HttpResponse, ApiError> {
+    user.session()?;
+    let (name, scopes) = body.into_inner().validate().map_err(ApiError::Validation)?;
+    let created = auth::create_api_key(&data, user.id(), name, scopes).await?;
+    Ok(HttpResponse::Created().json(created))
+}
+
+async fn list_api_keys(data: TenantState, user: CurrentUser) -> Result<HttpResponse, ApiError> {
+    user.session()?;
+    let keys = data.api_keys.list(user.id()).await?;
+    Ok(HttpResponse::Ok().json(keys))
+}
+
+async fn revoke_api_key(
+    data: TenantState,
+    user: CurrentUser,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    user.session()?;
+    let id = parse_object_id(&path.into_inner())?;
+    
+    if data.api_keys.revoke(user.id(), id).await? {
+        Ok(HttpResponse::NoContent().finish())
+    } else {
+        Err(ApiError::NotFound("API key"))
     }
 }
 
-async fn delete_task(data: web::Data<AppState>, path: web::Path<String>) -> impl Responder {
-    let id = path.into_inner();
-    let object_id = match mongodb::bson::oid::ObjectId::parse_str(&id) {
-        Ok(oid) => oid,
-        Err(_) => return HttpResponse::BadRequest().body("Invalid ID"),
+async fn get_lists(data: TenantState, user: CurrentUser) -> Result<HttpResponse, ApiError> {
+    let lists = data.lists.for_member(user.id()).await?;
+    Ok(HttpResponse::Ok().json(lists))
+}
+
+async fn create_list(
+    data: TenantState,
+    user: CurrentUser,
+    body: web::Json<ListPayload>,
+) -> Result<HttpResponse, ApiError> {
+    let name = body.into_inner().validate().map_err(ApiError::Validation)?;
+    let now = Utc::now();
+    let list = TaskList {
+        id: ObjectId::new(),
+        name,
+        members: vec![ListMember { user_id: user.id(), role: ListRole::Owner }],
+        created_at: now,
+        updated_at: now,
     };
     
-    let filter = doc! { "_id": object_id };
+    data.lists.create(&list).await?;
+    Ok(HttpResponse::Created().insert_header((header::LOCATION, format!("/lists/{}", list.id))).json(list))
+}
+
+async fn get_list(
+    data: TenantState,
+    user: CurrentUser,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let id = parse_object_id(&path.into_inner())?;
+    let list = lists::require(&data, user.id(), id, ListRole::Viewer).await?;
+    Ok(HttpResponse::Ok().json(list))
+}
+
+async fn rename_list(
+    data: TenantState,
+    user: CurrentUser,
+    path: web::Path<String>,
+    body: web::Json<ListPayload>,
+) -> Result<HttpResponse, ApiError> {
+    let id = parse_object_id(&path.into_inner())?;
+    let name = body.into_inner().validate().map_err(ApiError::Validation)?;
+    lists::require(&data, user.id(), id, ListRole::Owner).await?;
+    
+    let list = data.lists.rename(id, &name).await?.ok_or(ApiError::NotFound("list"))?;
+    Ok(HttpResponse::Ok().json(list))
+}
+
+/// Deletes a list together with its tasks.
+async fn delete_list(
+    data: TenantState,
+    user: CurrentUser,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let id = parse_object_id(&path.into_inner())?;
+    lists::require(&data, user.id(), id, ListRole::Owner).await?;
     
-    match data.tasks_collection.delete_one(filter).await {
-        Ok(result) => {
-            if result.deleted_count > 0 {
-                HttpResponse::Ok().finish()
-            } else {
-                HttpResponse::NotFound().finish()
-            }
-        }
-        Err(_) => HttpResponse::InternalServerError().finish(),
+    data.lists.delete(id).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Shares a list with the account registered under `email`, or changes the
+/// role of an existing member.
+async fn set_list_member(
+    data: TenantState,
+    user: CurrentUser,
+    path: web::Path<String>,
+    body: web::Json<MemberPayload>,
+) -> Result<HttpResponse, ApiError> {
+    let id = parse_object_id(&path.into_inner())?;
+    let (email, role) = body.into_inner().validate().map_err(ApiError::Validation)?;
+    lists::require(&data, user.id(), id, ListRole::Owner).await?;
+    
+    let Some(member) = data.users.find_by_email(&email).await? else {
+        let mut errors = FieldErrors::default();
+        errors.add("email", "must be the email of an existing user");
+        return Err(ApiError::Validation(errors));
+    };
+    
+    let list = data.lists.set_member(id, member.id, role).await?.ok_or(ApiError::NotFound("list"))?;
+    Ok(HttpResponse::Ok().json(list))
+}
+
+/// Removes a member from a list. Owners may remove anyone; other members
+/// may only leave.
+async fn remove_list_member(
+    data: TenantState,
+    user: CurrentUser,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, ApiError> {
+    let (id, member_id) = path.into_inner();
+    let id = parse_object_id(&id)?;
+    let member_id = parse_object_id(&member_id)?;
+    let required = if member_id == user.id() { ListRole::Viewer } else { ListRole::Owner };
+    let list = lists::require(&data, user.id(), id, required).await?;
+    
+    if list.role_of(&member_id).is_none() {
+        return Err(ApiError::NotFound("member"));
     }
+    
+    data.lists.remove_member(id, member_id).await?.ok_or(ApiError::NotFound("list"))?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+async fn get_list_tasks(
+    data: TenantState,
+    user: CurrentUser,
+    path: web::Path<String>,
+    req: HttpRequest,
+    params: web::Query<Vec<(String, String)>>,
+    if_none_match: Option<web::Header<IfNoneMatch>>,
+) -> Result<HttpResponse, ApiError> {
+    let id = parse_object_id(&path.into_inner())?;
+    lists::require(&data, user.id(), id, ListRole::Viewer).await?;
+    list_tasks(&data, &TaskScope::list(id), &req, &params, if_none_match.as_deref()).await
+}
+
+async fn add_list_task(
+    data: TenantState,
+    user: CurrentUser,
+    path: web::Path<String>,
+    req: HttpRequest,
+    body: web::Json<serde_json::Value>,
+) -> Result<HttpResponse, ApiError> {
+    let id = parse_object_id(&path.into_inner())?;
+    lists::require(&data, user.id(), id, ListRole::Editor).await?;
+    add_task_to(&data, &user, Some(id), &req, body.into_inner()).await
+}
+
+/// Tags used by the tasks the user can see, most used first.
+async fn get_tags(data: TenantState, user: CurrentUser) -> Result<HttpResponse, ApiError> {
+    let scope = Access::load(&data, user.id()).await?.scope(ListRole::Viewer);
+    let tags = data.tasks.tag_counts(&scope).await?;
+    Ok(HttpResponse::Ok().json(tags))
+}
+
+/// Renames a tag on every task the user can edit. Tasks that already carry
+/// the new name keep it once.
+async fn rename_tag(
+    data: TenantState,
+    user: CurrentUser,
+    path: web::Path<String>,
+    body: web::Json<TagRenamePayload>,
+) -> Result<HttpResponse, ApiError> {
+    let tag = path.into_inner();
+    let tag = validation::tag_value(&tag)
+        .map_err(|message| ApiError::InvalidRequest(vec![format!("tag '{}' {}", tag, message)]))?;
+    let name = body.into_inner().validate().map_err(ApiError::Validation)?;
+    
+    let scope = Access::load(&data, user.id()).await?.scope(ListRole::Editor);
+    let updated = data.tasks.rename_tags(&scope, &[tag], &name).await?;
+    Ok(HttpResponse::Ok().json(RetaggedTasks { updated }))
+}
+
+/// Replaces several tags by one on every task the user can edit.
+async fn merge_tags(
+    data: TenantState,
+    user: CurrentUser,
+    body: web::Json<TagMergePayload>,
+) -> Result<HttpResponse, ApiError> {
+    let (tags, into) = body.into_inner().validate().map_err(ApiError::Validation)?;
+    let scope = Access::load(&data, user.id()).await?.scope(ListRole::Editor);
+    let updated = data.tasks.rename_tags(&scope, &tags, &into).await?;
+    Ok(HttpResponse::Ok().json(RetaggedTasks { updated }))
+}
+
+async fn list_users(
+    data: TenantState,
+    params: web::Query<UserListParams>,
+) -> Result<HttpResponse, ApiError> {
+    params.validate()?;
+    let page = data.users.list(params.offset(), params.limit()).await?;
+    Ok(HttpResponse::Ok().json(UserPageResponse::new(page, &params)))
+}
+
+async fn get_user_tasks(
+    data: TenantState,
+    path: web::Path<String>,
+    req: HttpRequest,
+    params: web::Query<Vec<(String, String)>>,
+    if_none_match: Option<web::Header<IfNoneMatch>>,
+) -> Result<HttpResponse, ApiError> {
+    let id = parse_object_id(&path.into_inner())?;
+    if data.users.get(id).await?.is_none() {
+        return Err(ApiError::NotFound("user"));
+    }
+    list_tasks(&data, &TaskScope::personal(id), &req, &params, if_none_match.as_deref()).await
+}
+
+/// Tasks from before accounts existed, which no user can see until an admin
+/// transfers them.
+async fn get_unowned_tasks(
+    data: TenantState,
+    req: HttpRequest,
+    params: web::Query<Vec<(String, String)>>,
+    if_none_match: Option<web::Header<IfNoneMatch>>,
+) -> Result<HttpResponse, ApiError> {
+    list_tasks(&data, &TaskScope::unowned(), &req, &params, if_none_match.as_deref()).await
+}
+
+async fn disable_user(
+    data: TenantState,
+    admin: CurrentUser,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let id = parse_object_id(&path.into_inner())?;
+    if id == admin.id() {
+        return Err(ApiError::Conflict("Admins cannot disable their own account".to_string()));
+    }
+    
+    let user = data.users.set_disabled(id, Some(Utc::now())).await?.ok_or(ApiError::NotFound("user"))?;
+    data.refresh_tokens.revoke_user(id).await?;
+    Ok(HttpResponse::Ok().json(user))
+}
+
+async fn enable_user(data: TenantState, path: web::Path<String>) -> Result<HttpResponse, ApiError> {
+    let id = parse_object_id(&path.into_inner())?;
+    let user = data.users.set_disabled(id, None).await?.ok_or(ApiError::NotFound("user"))?;
+    Ok(HttpResponse::Ok().json(user))
+}
+
+async fn transfer_task(
+    data: TenantState,
+    path: web::Path<String>,
+    body: web::Json<TransferRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let id = parse_object_id(&path.into_inner())?;
+    let owner = parse_object_id(&body.owner_id)?;
+    if data.users.get(owner).await?.is_none() {
+        let mut errors = FieldErrors::default();
+        errors.add("owner_id", "must be the id of an existing user");
+        return Err(ApiError::Validation(errors));
+    }
+    
+    let task = data.tasks.transfer(id, owner).await?.ok_or(ApiError::NotFound("task"))?;
+    Ok(HttpResponse::Ok().insert_header(ETag(etag::task_etag(&task))).json(task))
+}
+
+async fn not_found() -> Result<HttpResponse, ApiError> {
+    Err(ApiError::NotFound("resource"))
 }
 
 async fn index() -> impl Responder {
     HttpResponse::Ok().body("Welcome to Rust Backend API! Visit /tasks to see all tasks.")
 }
 
+/// Opens the MongoDB collections of `tenant`, in a database of its own or
+/// in the shared one, depending on `strategy`.
+async fn connect_mongo(
+    client: &Client,
+    strategy: TenantStrategy,
+    tenant: Option<&str>,
+    idempotency_ttl: Duration,
+) -> AppState {
+    let (database, scope) = match (tenant, strategy) {
+        (Some(tenant), TenantStrategy::Database) => (client.database(&tenant_database(DATABASE_NAME, tenant)), None),
+        (tenant, _) => (client.database(DATABASE_NAME), tenant.map(str::to_string)),
+    };
+    let collection = |name: &str| TenantCollection::new(database.collection(name), scope.clone());
+    
+    let tasks = MongoTaskRepository::new(collection("tasks"));
+    tasks.ensure_indexes().await.expect("Failed to create MongoDB indexes");
+    let idempotency = MongoIdempotencyRepository::new(collection("idempotency_keys"), idempotency_ttl);
+    idempotency.ensure_indexes().await.expect("Failed to create MongoDB indexes");
+    let users = MongoUserRepository::new(collection("users"));
+    users.ensure_indexes().await.expect("Failed to create MongoDB indexes");
+    let refresh_tokens = MongoRefreshTokenRepository::new(collection("refresh_tokens"));
+    refresh_tokens.ensure_indexes().await.expect("Failed to create MongoDB indexes");
+    let revoked_tokens = MongoRevokedTokenRepository::new(collection("revoked_tokens"));
+    revoked_tokens.ensure_indexes().await.expect("Failed to create MongoDB indexes");
+    let api_keys = MongoApiKeyRepository::new(collection("api_keys"));
+    api_keys.ensure_indexes().await.expect("Failed to create MongoDB indexes");
+    let lists = MongoListRepository::new(collection("lists"), collection("tasks"));
+    lists.ensure_indexes().await.expect("Failed to create MongoDB indexes");
+    
+    AppState {
+        tenant: tenant.map(str::to_string),
+        tasks: Arc::new(tasks),
+        idempotency: Arc::new(idempotency),
+        users: Arc::new(users),
+        refresh_tokens: Arc::new(refresh_tokens),
+        revoked_tokens: Arc::new(revoked_tokens),
+        api_keys: Arc::new(api_keys),
+        lists: Arc::new(lists),
+    }
+}
+
+/// The SQLite file of `tenant`, next to `path`: `data/app.db` becomes
+/// `data/app_acme.db`.
+fn tenant_path(path: &str, tenant: &str) -> String {
+    let path = Path::new(path);
+    let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or(DATABASE_NAME);
+    let name = match path.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) => format!("{}_{}.{}", stem, tenant, extension),
+        None => format!("{}_{}", stem, tenant),
+    };
+    path.with_file_name(name).to_string_lossy().into_owned()
+}
+
+/// Where data is stored, chosen with `STORAGE_BACKEND`.
+enum Backend {
+    Mongo(Client, TenantStrategy),
+    Memory,
+    Sqlite(String),
+}
+
+impl Backend {
+    async fn from_env(strategy: TenantStrategy) -> Self {
+        let backend = std::env::var("STORAGE_BACKEND").unwrap_or_else(|_| "mongodb".to_string());
+        if strategy == TenantStrategy::Shared && backend != "mongodb" {
+            panic!("TENANT_STRATEGY=shared is only supported with STORAGE_BACKEND=mongodb");
+        }
+        
+        match backend.as_str() {
+            "mongodb" => {
+                let mongodb_uri = std::env::var("MONGODB_URI").expect("MONGODB_URI must be set in .env file");
+                let client = Client::with_uri_str(&mongodb_uri)
+                    .await
+                    .expect("Failed to connect to MongoDB");
+                println!("Connected to MongoDB!");
+                Backend::Mongo(client, strategy)
+            }
+            "memory" => {
+                println!("Using in-memory task storage, data will not survive a restart");
+                Backend::Memory
+            }
+            "sqlite" => Backend::Sqlite(std::env::var("SQLITE_PATH").unwrap_or_else(|_| "rust_backend.db".to_string())),
+            other => panic!("Unknown STORAGE_BACKEND '{}', expected 'mongodb', 'memory' or 'sqlite'", other),
+        }
+    }
+
+    /// Opens the storage of `tenant`, or of the whole server when it hosts a
+    /// single organization.
+    async fn open(&self, tenant: Option<&str>, idempotency_ttl: Duration) -> AppState {
+        match self {
+            Backend::Mongo(client, strategy) => connect_mongo(client, *strategy, tenant, idempotency_ttl).await,
+            Backend::Memory => {
+                let tasks = Arc::new(MemoryTaskRepository::new());
+                AppState {
+                    tenant: tenant.map(str::to_string),
+                    tasks: tasks.clone(),
+                    idempotency: Arc::new(MemoryIdempotencyRepository::new(idempotency_ttl)),
+                    users: Arc::new(MemoryUserRepository::new()),
+                    refresh_tokens: Arc::new(MemoryRefreshTokenRepository::new()),
+                    revoked_tokens: Arc::new(MemoryRevokedTokenRepository::new()),
+                    api_keys: Arc::new(MemoryApiKeyRepository::new()),
+                    lists: Arc::new(MemoryListRepository::new(tasks)),
+                }
+            }
+            Backend::Sqlite(path) => {
+                let path = match tenant {
+                    Some(tenant) => tenant_path(path, tenant),
+                    None => path.clone(),
+                };
+                let db = SqliteDatabase::open(&path).expect("Failed to open SQLite database");
+                println!("Using SQLite database at {}", path);
+                AppState {
+                    tenant: tenant.map(str::to_string),
+                    tasks: Arc::new(SqliteTaskRepository::new(db.clone())),
+                    idempotency: Arc::new(SqliteIdempotencyRepository::new(db.clone(), idempotency_ttl)),
+                    users: Arc::new(SqliteUserRepository::new(db.clone())),
+                    refresh_tokens: Arc::new(SqliteRefreshTokenRepository::new(db.clone())),
+                    revoked_tokens: Arc::new(SqliteRevokedTokenRepository::new(db.clone())),
+                    api_keys: Arc::new(SqliteApiKeyRepository::new(db.clone())),
+                    lists: Arc::new(SqliteListRepository::new(db)),
+                }
+            }
+        }
+    }
+}
+
+/// Gives the admin role to the already registered accounts listed in
+/// `ADMIN_EMAILS`; accounts registered later get it on registration.
+async fn grant_admin_roles(users: &dyn UserRepository, emails: &[String]) {
+    for email in emails {
+        let user = users.find_by_email(email).await.expect("Failed to look up admin accounts");
+        if let Some(user) = user.filter(|user| user.role != Role::Admin) {
+            users.set_role(user.id, Role::Admin).await.expect("Failed to grant the admin role");
+            println!("Granted the admin role to {}", email);
+        }
+    }
+}
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenvy::dotenv().ok();
     
-    let mongodb_uri = std::env::var("MONGODB_URI").expect("MONGODB_URI must be set in .env file");
-    
-    let client = Client::with_uri_str(&mongodb_uri)
-        .await
-        .expect("Failed to connect to MongoDB");
+    let idempotency_ttl = std::env::var("IDEMPOTENCY_TTL_SECONDS")
+        .map(|ttl| ttl.parse::<i64>().expect("IDEMPOTENCY_TTL_SECONDS must be a number of seconds"))
+        .map(Duration::seconds)
+        .unwrap_or(Duration::seconds(idempotency::DEFAULT_TTL_SECONDS));
     
-    let database = client.database("rust_backend");
-    let tasks_collection = database.collection::<mongodb::bson::Document>("tasks");
+    let refresh_ttl = std::env::var("REFRESH_TOKEN_TTL_SECONDS")
+        .map(|ttl| ttl.parse::<i64>().expect("REFRESH_TOKEN_TTL_SECONDS must be a number of seconds"))
+        .map(Duration::seconds)
+        .unwrap_or(Duration::seconds(auth::DEFAULT_REFRESH_TTL_SECONDS));
+    let admin_emails: Vec<String> = std::env::var("ADMIN_EMAILS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|email| email.trim().to_lowercase())
+        .filter(|email| !email.is_empty())
+        .collect();
     
-    let app_data = web::Data::new(AppState { tasks_collection });
+    let backend = Backend::from_env(TenantStrategy::from_env()).await;
+    let tenant_ids = tenant::tenants_from_env();
+    let tenants = if tenant_ids.is_empty() {
+        let state = backend.open(None, idempotency_ttl).await;
+        grant_admin_roles(state.users.as_ref(), &admin_emails).await;
+        Tenants::Single(Arc::new(state))
+    } else {
+        let mut states = HashMap::new();
+        for tenant in tenant_ids {
+            let state = backend.open(Some(&tenant), idempotency_ttl).await;
+            grant_admin_roles(state.users.as_ref(), &admin_emails).await;
+            states.insert(tenant, Arc::new(state));
+        }
+        let base_domain = std::env::var("TENANT_BASE_DOMAIN")
+            .ok()
+            .map(|domain| domain.trim().trim_start_matches('.').to_lowercase())
+            .filter(|domain| !domain.is_empty());
+        println!("Hosting {} tenants", states.len());
+        Tenants::Multi { states, base_domain }
+    };
+    let app_data = web::Data::new(tenants);
+    let auth_config = web::Data::new(AuthConfig { jwt: JwtSettings::from_env(), refresh_ttl, admin_emails });
 
     let host = "0.0.0.0";
     let port = std::env::var("PORT")
@@ -116,8 +994,11 @@ async fn main() -> std::io::Result<()> {
         .parse::<u16>()
         .expect("PORT must be a valid number");
 
+    let json_body_limit = std::env::var("JSON_BODY_LIMIT")
+        .map(|limit| limit.parse::<usize>().expect("JSON_BODY_LIMIT must be a number of bytes"))
+        .unwrap_or(DEFAULT_JSON_BODY_LIMIT);
+
     println!("Server running at http://{}:{}/", host, port);
-    println!("Connected to MongoDB!");
 
     HttpServer::new(move || {
         let cors = Cors::permissive();
@@ -125,13 +1006,82 @@ async fn main() -> std::io::Result<()> {
         App::new()
             .wrap(cors)
             .app_data(app_data.clone())
+            .app_data(auth_config.clone())
+            .app_data(web::JsonConfig::default().limit(json_body_limit).error_handler(move |err, _req| {
+                match err {
+                    JsonPayloadError::Overflow { .. } | JsonPayloadError::OverflowKnownLength { .. } => {
+                        ApiError::PayloadTooLarge(json_body_limit).into()
+                    }
+                    err => ApiError::InvalidRequest(vec![err.to_string()]).into(),
+                }
+            }))
+            .app_data(web::QueryConfig::default().error_handler(|err, _req| {
+                ApiError::InvalidRequest(vec![err.to_string()]).into()
+            }))
             .route("/", web::get().to(index))
-            .route("/tasks", web::get().to(get_tasks))
-            .route("/tasks", web::post().to(add_task))
-            .route("/tasks/{id}", web::put().to(update_task))
-            .route("/tasks/{id}", web::delete().to(delete_task))
+            .route("/auth/register", web::post().to(register))
+            .route("/auth/login", web::post().to(login))
+            .route("/auth/refresh", web::post().to(refresh))
+            .route("/auth/logout", web::post().to(logout))
+            .route("/auth/me", web::get().to(current_user))
+            .route("/auth/api-keys", web::post().to(create_api_key))
+            .route("/auth/api-keys", web::get().to(list_api_keys))
+            .route("/auth/api-keys/{id}", web::delete().to(revoke_api_key))
+            .service(
+                web::scope("/tasks")
+                    .wrap(from_fn(auth::require_task_scope))
+                    .route("", web::get().to(get_tasks))
+                    .route("", web::post().to(add_task))
+                    .route("", web::delete().to(delete_tasks))
+                    .route("/bulk", web::post().to(bulk_tasks))
+                    .route("/search", web::get().to(search_tasks))
+                    .route("/{id}", web::get().to(get_task))
+                    .route("/{id}", web::put().to(update_task))
+                    .route("/{id}", web::patch().to(patch_task))
+                    .route("/{id}", web::delete().to(delete_task))
+                    .route("/{id}/complete", web::post().to(complete_task))
+                    .route("/{id}/reopen", web::post().to(reopen_task))
+                    .route("/{id}/tags", web::post().to(add_task_tags))
+                    .route("/{id}/tags/{tag}", web::delete().to(remove_task_tag))
+                    .route("/{id}/checklist", web::post().to(add_checklist_item))
+                    .route("/{id}/checklist/{item_id}", web::patch().to(patch_checklist_item))
+                    .route("/{id}/checklist/{item_id}", web::delete().to(remove_checklist_item))
+                    .route("/{id}/checklist/{item_id}/check", web::post().to(check_checklist_item))
+                    .route("/{id}/checklist/{item_id}/uncheck", web::post().to(uncheck_checklist_item)),
+            )
+            .service(
+                web::scope("/tags")
+                    .wrap(from_fn(auth::require_task_scope))
+                    .route("", web::get().to(get_tags))
+                    .route("/merge", web::post().to(merge_tags))
+                    .route("/{tag}", web::patch().to(rename_tag)),
+            )
+            .service(
+                web::scope("/lists")
+                    .wrap(from_fn(auth::require_task_scope))
+                    .route("", web::get().to(get_lists))
+                    .route("", web::post().to(create_list))
+                    .route("/{id}", web::get().to(get_list))
+                    .route("/{id}", web::patch().to(rename_list))
+                    .route("/{id}", web::delete().to(delete_list))
+                    .route("/{id}/members", web::post().to(set_list_member))
+                    .route("/{id}/members/{user_id}", web::delete().to(remove_list_member))
+                    .route("/{id}/tasks", web::get().to(get_list_tasks))
+                    .route("/{id}/tasks", web::post().to(add_list_task)),
+            )
+            .service(
+                web::scope("/admin")
+                    .wrap(from_fn(auth::require_admin))
+                    .route("/users", web::get().to(list_users))
+                    .route("/users/{id}/tasks", web::get().to(get_user_tasks))
+                    .route("/users/{id}/disable", web::post().to(disable_user))
+                    .route("/users/{id}/enable", web::post().to(enable_user))
+                    .route("/tasks/unowned", web::get().to(get_unowned_tasks))
+                    .route("/tasks/{id}/transfer", web::post().to(transfer_task)),
+            )
+            .default_service(web::to(not_found))
     })
     .bind((host, port))?
     .run()
     .await
-}
\ No newline at end of file
+}
@@ -0,0 +1,553 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use mongodb::bson::serde_helpers::serialize_object_id_as_hex_string;
+use serde::{Serialize, Deserialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    Medium,
+    High,
+    Urgent,
+}
+
+impl Priority {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Priority::Low => "low",
+            Priority::Medium => "medium",
+            Priority::High => "high",
+            Priority::Urgent => "urgent",
+        }
+    }
+}
+
+impl FromStr for Priority {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "low" => Ok(Priority::Low),
+            "medium" => Ok(Priority::Medium),
+            "high" => Ok(Priority::High),
+            "urgent" => Ok(Priority::Urgent),
+            other => Err(format!("unknown priority '{}'", other)),
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct Task {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// Id of the user the task belongs to. Tasks created before accounts
+    /// existed have none; only admins can see them, via
+    /// `/admin/tasks/unowned`, and transfer them to a user.
+    pub owner_id: Option<String>,
+    /// Id of the shared list the task is in. Tasks in no list are personal
+    /// and only visible to their owner.
+    pub list_id: Option<String>,
+    /// Id of the task this one is a subtask of, which is always in the same
+    /// list.
+    pub parent_id: Option<String>,
+    pub title: String,
+    pub description: Option<String>,
+    pub completed: bool,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub due_at: Option<DateTime<Utc>>,
+    pub priority: Option<Priority>,
+    /// Lowercased labels, without duplicates, in the order they were added.
+    pub tags: Vec<String>,
+    /// Ordered by `position`.
+    pub checklist: Vec<ChecklistItem>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Incremented on every write, starting at 1 for new tasks. Tasks stored
+    /// before revisions were tracked read as 0 until their next write.
+    pub revision: i64,
+    /// Checked items and completed subtasks out of all of them. Only filled
+    /// in on task listings.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress: Option<Progress>,
+}
+
+impl Task {
+    pub fn is_owned_by(&self, owner: &ObjectId) -> bool {
+        self.owner_id.as_deref() == Some(owner.to_hex().as_str())
+    }
+
+    /// Applies the user-editable fields present in `changes`. Completing an
+    /// already completed task keeps its original `completed_at`.
+    pub fn apply(&mut self, changes: TaskChanges, now: DateTime<Utc>) {
+        if let Some(title) = changes.title {
+            self.title = title;
+        }
+        if let Some(description) = changes.description {
+            self.description = description;
+        }
+        if let Some(completed) = changes.completed {
+            self.completed = completed;
+            self.completed_at = if completed { self.completed_at.or(Some(now)) } else { None };
+        }
+        if let Some(due_at) = changes.due_at {
+            self.due_at = due_at;
+        }
+        if let Some(priority) = changes.priority {
+            self.priority = priority;
+        }
+        if let Some(tags) = changes.tags {
+            self.tags = tags;
+        }
+        if let Some(parent_id) = changes.parent_id {
+            self.parent_id = parent_id.map(|parent| parent.to_hex());
+        }
+        if let Some(checklist) = changes.checklist {
+            self.checklist = checklist;
+        }
+    }
+
+    /// Adds the tags the task does not carry yet, after the existing ones.
+    pub fn add_tags(&mut self, tags: &[String]) {
+        for tag in tags {
+            if !self.tags.contains(tag) {
+                self.tags.push(tag.clone());
+            }
+        }
+    }
+
+    pub fn remove_tags(&mut self, tags: &[String]) {
+        self.tags.retain(|tag| !tags.contains(tag));
+    }
+
+    /// Replaces each tag in `from` by `to`, keeping only the first occurrence
+    /// of `to`. Returns whether any tag was replaced.
+    pub fn rename_tags(&mut self, from: &[String], to: &str) -> bool {
+        if !self.tags.iter().any(|tag| from.contains(tag)) {
+            return false;
+        }
+        let renamed = self.tags.iter().map(|tag| if from.contains(tag) { to } else { tag.as_str() });
+        let mut tags: Vec<String> = Vec::with_capacity(self.tags.len());
+        for tag in renamed {
+            if !tags.iter().any(|existing| existing == tag) {
+                tags.push(tag.to_string());
+            }
+        }
+        self.tags = tags;
+        true
+    }
+}
+
+/// A set of edits to a task's user-editable fields. `None` leaves a field
+/// untouched; for optional fields `Some(None)` clears it.
+#[derive(Clone, Default)]
+pub struct TaskChanges {
+    pub title: Option<String>,
+    pub description: Option<Option<String>>,
+    pub completed: Option<bool>,
+    pub due_at: Option<Option<DateTime<Utc>>>,
+    pub priority: Option<Option<Priority>>,
+    pub tags: Option<Vec<String>>,
+    pub parent_id: Option<Option<ObjectId>>,
+    pub checklist: Option<Vec<ChecklistItem>>,
+}
+
+impl TaskChanges {
+    pub fn completion(completed: bool) -> Self {
+        TaskChanges { completed: Some(completed), ..Self::default() }
+    }
+}
+
+/// Replacing a task sets every user-editable field, clearing omitted ones.
+/// The checklist is edited through endpoints of its own and left alone.
+impl From<TaskInput> for TaskChanges {
+    fn from(input: TaskInput) -> Self {
+        TaskChanges {
+            title: Some(input.title),
+            description: Some(input.description),
+            completed: Some(input.completed),
+            due_at: Some(input.due_at),
+            priority: Some(input.priority),
+            tags: Some(input.tags),
+            parent_id: Some(input.parent_id),
+            checklist: None,
+        }
+    }
+}
+
+/// Request body for creating or replacing a task, as sent by the client.
+/// Values are kept loosely typed so that [`TaskPayload::validate`] can report
+/// every problem per field instead of failing on the first bad value.
+#[derive(Deserialize)]
+pub struct TaskPayload {
+    #[serde(rename = "_id")]
+    pub id: Option<serde_json::Value>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub completed: bool,
+    /// RFC 3339 timestamp.
+    pub due_at: Option<String>,
+    pub priority: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub parent_id: Option<String>,
+}
+
+/// Validated task content. Server-managed fields (`_id`, timestamps,
+/// `revision`) are not part of it.
+#[derive(Clone)]
+pub struct TaskInput {
+    pub title: String,
+    pub description: Option<String>,
+    pub completed: bool,
+    pub due_at: Option<DateTime<Utc>>,
+    pub priority: Option<Priority>,
+    pub tags: Vec<String>,
+    pub parent_id: Option<ObjectId>,
+}
+
+impl TaskInput {
+    pub fn into_task(self, id: ObjectId, owner: ObjectId, list: Option<ObjectId>, now: DateTime<Utc>) -> Task {
+        Task {
+            id: Some(id.to_hex()),
+            owner_id: Some(owner.to_hex()),
+            list_id: list.map(|list| list.to_hex()),
+            parent_id: self.parent_id.map(|parent| parent.to_hex()),
+            title: self.title,
+            description: self.description,
+            completed: self.completed,
+            completed_at: self.completed.then_some(now),
+            due_at: self.due_at,
+            priority: self.priority,
+            tags: self.tags,
+            checklist: Vec::new(),
+            created_at: now,
+            updated_at: now,
+            revision: 1,
+            progress: None,
+        }
+    }
+}
+
+/// A step of a task's checklist.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ChecklistItem {
+    pub id: String,
+    pub text: String,
+    pub done: bool,
+    /// Zero-based place in the checklist; the items of a task always number
+    /// 0, 1, 2 and so on.
+    pub position: u32,
+}
+
+/// Request body of `POST /tasks/{id}/checklist`.
+#[derive(Deserialize)]
+pub struct ChecklistItemPayload {
+    pub text: Option<String>,
+    /// Where to insert the item; appended when omitted.
+    pub position: Option<u32>,
+}
+
+/// Validated edits to a checklist item; `None` leaves a field untouched.
+#[derive(Default)]
+pub struct ChecklistItemChanges {
+    pub text: Option<String>,
+    pub done: Option<bool>,
+    pub position: Option<u32>,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Progress {
+    pub done: u64,
+    pub total: u64,
+}
+
+impl Progress {
+    /// Progress of the task's checklist, plus that of its `subtasks`.
+    pub fn of(task: &Task, subtasks: Progress) -> Self {
+        let done = task.checklist.iter().filter(|item| item.done).count() as u64;
+        Progress { done: done + subtasks.done, total: task.checklist.len() as u64 + subtasks.total }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    #[default]
+    User,
+    /// May use the `/admin` endpoints.
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::User => "user",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "user" => Ok(Role::User),
+            "admin" => Ok(Role::Admin),
+            other => Err(format!("unknown role '{}'", other)),
+        }
+    }
+}
+
+/// A registered account. The password hash never leaves the server.
+#[derive(Serialize, Clone)]
+pub struct User {
+    #[serde(rename = "_id", serialize_with = "serialize_object_id_as_hex_string")]
+    pub id: ObjectId,
+    /// Stored lowercased, so lookups are case-insensitive.
+    pub email: String,
+    #[serde(skip_serializing)]
+    pub password_hash: String,
+    pub role: Role,
+    /// Set while an admin has disabled the account, which then can neither
+    /// log in nor use its tokens and API keys.
+    pub disabled_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl User {
+    pub fn is_disabled(&self) -> bool {
+        self.disabled_at.is_some()
+    }
+}
+
+/// Request body of `POST /auth/register` and `POST /auth/login`.
+#[derive(Deserialize)]
+pub struct Credentials {
+    pub email: Option<String>,
+    pub password: Option<String>,
+}
+
+/// Permission carried by an API key. Login sessions are not limited by
+/// scopes.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Scope {
+    #[serde(rename = "tasks:read")]
+    TasksRead,
+    #[serde(rename = "tasks:write")]
+    TasksWrite,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::TasksRead => "tasks:read",
+            Scope::TasksWrite => "tasks:write",
+        }
+    }
+}
+
+impl FromStr for Scope {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "tasks:read" => Ok(Scope::TasksRead),
+            "tasks:write" => Ok(Scope::TasksWrite),
+            other => Err(format!("unknown scope '{}'", other)),
+        }
+    }
+}
+
+/// A key for non-interactive access on behalf of a user. Only a hash of the
+/// key is stored; the key itself is shown once, when it is created.
+#[derive(Serialize, Clone)]
+pub struct ApiKey {
+    #[serde(rename = "_id", serialize_with = "serialize_object_id_as_hex_string")]
+    pub id: ObjectId,
+    #[serde(skip_serializing)]
+    pub user_id: ObjectId,
+    pub name: String,
+    /// Leading characters of the key, so that keys can be told apart.
+    pub prefix: String,
+    #[serde(skip_serializing)]
+    pub key_hash: String,
+    pub scopes: Vec<Scope>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// Request body of `POST /auth/api-keys`.
+#[derive(Deserialize)]
+pub struct ApiKeyPayload {
+    pub name: Option<String>,
+    pub scopes: Option<Vec<String>>,
+}
+
+/// Role of a member of a shared list, in increasing order of what it allows:
+/// viewers read the tasks, editors also write them, and owners also manage
+/// the list and its members.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum ListRole {
+    Viewer,
+    Editor,
+    Owner,
+}
+
+impl ListRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ListRole::Viewer => "viewer",
+            ListRole::Editor => "editor",
+            ListRole::Owner => "owner",
+        }
+    }
+}
+
+impl FromStr for ListRole {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "viewer" => Ok(ListRole::Viewer),
+            "editor" => Ok(ListRole::Editor),
+            "owner" => Ok(ListRole::Owner),
+            other => Err(format!("unknown list role '{}'", other)),
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct ListMember {
+    #[serde(serialize_with = "serialize_object_id_as_hex_string")]
+    pub user_id: ObjectId,
+    pub role: ListRole,
+}
+
+/// A list of tasks shared between its members.
+#[derive(Serialize, Clone)]
+pub struct TaskList {
+    #[serde(rename = "_id", serialize_with = "serialize_object_id_as_hex_string")]
+    pub id: ObjectId,
+    pub name: String,
+    pub members: Vec<ListMember>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl TaskList {
+    pub fn role_of(&self, user_id: &ObjectId) -> Option<ListRole> {
+        self.members.iter().find(|member| member.user_id == *user_id).map(|member| member.role)
+    }
+
+    pub fn owner_count(&self) -> usize {
+        self.members.iter().filter(|member| member.role == ListRole::Owner).count()
+    }
+}
+
+/// Request body of `POST /lists` and `PATCH /lists/{id}`.
+#[derive(Deserialize)]
+pub struct ListPayload {
+    pub name: Option<String>,
+}
+
+/// Request body of `POST /lists/{id}/members`.
+#[derive(Deserialize)]
+pub struct MemberPayload {
+    pub email: Option<String>,
+    pub role: Option<String>,
+}
+
+/// Request body of `POST /tasks/{id}/tags`.
+#[derive(Deserialize)]
+pub struct TagsPayload {
+    pub tags: Option<Vec<String>>,
+}
+
+/// Request body of `PATCH /tags/{tag}`.
+#[derive(Deserialize)]
+pub struct TagRenamePayload {
+    pub name: Option<String>,
+}
+
+/// Request body of `POST /tags/merge`.
+#[derive(Deserialize)]
+pub struct TagMergePayload {
+    pub tags: Option<Vec<String>>,
+    pub into: Option<String>,
+}
+
+/// Response body of `PATCH /tags/{tag}` and `POST /tags/merge`.
+#[derive(Serialize)]
+pub struct RetaggedTasks {
+    pub updated: u64,
+}
+
+/// Creation time of a task with no stored `created_at`, taken from the
+/// timestamp embedded in its ObjectId.
+pub fn created_at_from_id(id: &ObjectId) -> DateTime<Utc> {
+    DateTime::from_timestamp_millis(id.timestamp().timestamp_millis()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tagged(tags: &[&str]) -> Task {
+        let input = TaskInput {
+            title: "Task".to_string(),
+            description: None,
+            completed: false,
+            due_at: None,
+            priority: None,
+            tags: strings(tags),
+            parent_id: None,
+        };
+        input.into_task(ObjectId::new(), ObjectId::new(), None, Utc::now())
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn rename_replaces_the_tag_in_place() {
+        let mut task = tagged(&["a", "old", "b"]);
+        assert!(task.rename_tags(&strings(&["old"]), "new"));
+        assert_eq!(task.tags, ["a", "new", "b"]);
+    }
+
+    #[test]
+    fn rename_without_a_match_changes_nothing() {
+        let mut task = tagged(&["a", "b"]);
+        assert!(!task.rename_tags(&strings(&["old"]), "new"));
+        assert_eq!(task.tags, ["a", "b"]);
+    }
+
+    #[test]
+    fn rename_onto_a_carried_tag_keeps_the_first_occurrence() {
+        let mut task = tagged(&["new", "a", "old"]);
+        assert!(task.rename_tags(&strings(&["old"]), "new"));
+        assert_eq!(task.tags, ["new", "a"]);
+    }
+
+    #[test]
+    fn merge_replaces_every_source_tag_once() {
+        let mut task = tagged(&["bug", "a", "defect", "issue"]);
+        assert!(task.rename_tags(&strings(&["bug", "defect", "issue"]), "problem"));
+        assert_eq!(task.tags, ["problem", "a"]);
+    }
+
+    #[test]
+    fn add_and_remove_tags_skip_what_is_not_there() {
+        let mut task = tagged(&["a", "b"]);
+        task.add_tags(&strings(&["b", "c"]));
+        assert_eq!(task.tags, ["a", "b", "c"]);
+        task.remove_tags(&strings(&["a", "z"]));
+        assert_eq!(task.tags, ["b", "c"]);
+    }
+}
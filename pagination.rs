@@ -0,0 +1,67 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use mongodb::bson::oid::ObjectId;
+use serde::Serialize;
+
+use crate::models::Task;
+use crate::storage::{TaskPage, TaskQuery};
+
+/// Upper bound on `limit`, and the page size used when none is requested.
+pub const MAX_PAGE_SIZE: u64 = 100;
+
+/// Upper bound on `offset`, which the databases take as a signed integer.
+pub const MAX_OFFSET: u64 = i64::MAX as u64;
+
+#[derive(Serialize)]
+pub struct TaskPageResponse {
+    pub tasks: Vec<Task>,
+    pub total: u64,
+    pub limit: u64,
+    pub next_cursor: Option<String>,
+}
+
+impl TaskPageResponse {
+    pub fn new(page: TaskPage, query: &TaskQuery) -> Self {
+        // Cursors encode a position in `_id` order, so they are only offered
+        // for unsorted listings.
+        let next_cursor = if page.has_more && query.sort.is_empty() {
+            page.tasks
+                .last()
+                .and_then(|task| task.id.as_deref())
+                .and_then(|id| ObjectId::parse_str(id).ok())
+                .map(|id| encode_cursor(&id))
+        } else {
+            None
+        };
+
+        TaskPageResponse {
+            tasks: page.tasks,
+            total: page.total,
+            limit: query.limit,
+            next_cursor,
+        }
+    }
+}
+
+/// Value of the `Link` header of a bare-array listing that has more tasks:
+/// the same request with `offset` moved past the `returned` tasks.
+pub fn next_page_link(path: &str, query_string: &str, query: &TaskQuery, returned: usize) -> String {
+    let mut pairs: Vec<String> = query_string
+        .split('&')
+        .filter(|pair| !pair.is_empty() && !matches!(pair.split('=').next(), Some("offset" | "limit")))
+        .map(str::to_string)
+        .collect();
+    pairs.push(format!("offset={}", query.offset + returned as u64));
+    pairs.push(format!("limit={}", query.limit));
+    format!("<{}?{}>; rel=\"next\"", path, pairs.join("&"))
+}
+
+/// Cursors are the last seen id, base64 encoded so clients treat them as opaque.
+pub fn encode_cursor(id: &ObjectId) -> String {
+    URL_SAFE_NO_PAD.encode(id.bytes())
+}
+
+pub fn decode_cursor(cursor: &str) -> Option<ObjectId> {
+    let bytes: [u8; 12] = URL_SAFE_NO_PAD.decode(cursor).ok()?.try_into().ok()?;
+    Some(ObjectId::from_bytes(bytes))
+}
@@ -0,0 +1,262 @@
+use std::str::FromStr;
+
+use mongodb::bson::oid::ObjectId;
+
+use crate::models::Priority;
+use crate::pagination::{decode_cursor, MAX_OFFSET, MAX_PAGE_SIZE};
+use crate::storage::{SortKey, TaskFilter, TaskQuery};
+use crate::validation;
+
+/// Query string accepted by `GET /tasks`:
+///
+/// - paging: `limit`, `offset`, `cursor`, `envelope`
+/// - filters: `completed`, `priority` (comma separated), `due_before`, `due_after`,
+///   `tag` (comma separated, all required), `parent` (a task id, or `none` for
+///   tasks that are not a subtask)
+/// - ordering: `sort=field,-field`, `-` meaning descending
+pub struct ListParams {
+    pub query: TaskQuery,
+    /// Wraps the result in a `TaskPageResponse` instead of a bare array, which
+    /// reports the total in `X-Total-Count` and the next page in `Link`.
+    /// Paging with a cursor always does, since that is the only place the
+    /// next cursor can be reported.
+    pub envelope: bool,
+}
+
+impl ListParams {
+    /// Parses the raw query pairs, collecting every problem rather than
+    /// stopping at the first so clients can fix them in one go.
+    pub fn parse(pairs: &[(String, String)]) -> Result<Self, Vec<String>> {
+        let mut errors = Vec::new();
+        let mut limit = None;
+        let mut offset = None;
+        let mut after = None;
+        let mut envelope = false;
+        let mut filter = TaskFilter::default();
+        let mut sort = Vec::new();
+
+        for (key, value) in pairs {
+            match key.as_str() {
+                "limit" => limit = parse_value(key, value, &mut errors),
+                "offset" => offset = parse_value(key, value, &mut errors),
+                "cursor" => match decode_cursor(value) {
+                    Some(id) => after = Some(id),
+                    None => errors.push(format!("invalid value '{}' for 'cursor'", value)),
+                },
+                "envelope" => envelope = parse_value(key, value, &mut errors).unwrap_or(false),
+                "sort" => {
+                    for item in value.split(',') {
+                        let (descending, field) = match item.strip_prefix('-') {
+                            Some(field) => (true, field),
+                            None => (false, item),
+                        };
+                        if let Some(field) = parse_value(key, field, &mut errors) {
+                            sort.push(SortKey { field, descending });
+                        }
+                    }
+                }
+                _ => parse_filter_param(&mut filter, key, value, &mut errors),
+            }
+        }
+
+        if offset.is_some_and(|offset| offset > MAX_OFFSET) {
+            errors.push(format!("'offset' must be at most {}", MAX_OFFSET));
+        }
+        if after.is_some() && offset.is_some() {
+            errors.push("'cursor' and 'offset' cannot be combined".to_string());
+        }
+        if after.is_some() && !sort.is_empty() {
+            errors.push("'cursor' cannot be combined with 'sort'".to_string());
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(ListParams {
+            query: TaskQuery {
+                limit: limit.unwrap_or(MAX_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE),
+                offset: offset.unwrap_or(0),
+                after,
+                filter,
+                sort,
+            },
+            envelope: envelope || after.is_some(),
+        })
+    }
+}
+
+/// Parses the query string of `DELETE /tasks`, which only takes the filters of
+/// `GET /tasks`. At least one is required so that a bare `DELETE /tasks` cannot
+/// wipe every task by accident.
+pub fn parse_filter(pairs: &[(String, String)]) -> Result<TaskFilter, Vec<String>> {
+    let mut errors = Vec::new();
+    let mut filter = TaskFilter::default();
+
+    for (key, value) in pairs {
+        parse_filter_param(&mut filter, key, value, &mut errors);
+    }
+    if errors.is_empty() && filter.is_empty() {
+        errors.push("at least one filter is required".to_string());
+    }
+
+    if errors.is_empty() { Ok(filter) } else { Err(errors) }
+}
+
+/// Applies one filter parameter, reporting unknown keys and bad values.
+fn parse_filter_param(filter: &mut TaskFilter, key: &str, value: &str, errors: &mut Vec<String>) {
+    match key {
+        "completed" => filter.completed = parse_value(key, value, errors),
+        "priority" => {
+            for item in value.split(',') {
+                filter.priorities.extend(parse_value::<Priority>(key, item, errors));
+            }
+        }
+        "due_before" => filter.due_before = parse_value(key, value, errors),
+        "due_after" => filter.due_after = parse_value(key, value, errors),
+        "tag" => {
+            for item in value.split(',') {
+                match validation::tag_value(item) {
+                    Ok(tag) => filter.tags.push(tag),
+                    Err(_) => errors.push(format!("invalid value '{}' for '{}'", item, key)),
+                }
+            }
+        }
+        "parent" => match value {
+            "none" => filter.parent = Some(None),
+            id => match ObjectId::parse_str(id) {
+                Ok(id) => filter.parent = Some(Some(id)),
+                Err(_) => errors.push(format!("invalid value '{}' for '{}'", value, key)),
+            },
+        },
+        other => errors.push(format!("unknown query parameter '{}'", other)),
+    }
+}
+
+fn parse_value<T: FromStr>(key: &str, value: &str, errors: &mut Vec<String>) -> Option<T> {
+    match value.parse() {
+        Ok(parsed) => Some(parsed),
+        Err(_) => {
+            errors.push(format!("invalid value '{}' for '{}'", value, key));
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pagination::encode_cursor;
+    use crate::storage::SortField;
+
+    use super::*;
+
+    fn pairs(query: &[(&str, &str)]) -> Vec<(String, String)> {
+        query.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()
+    }
+
+    fn parse(query: &[(&str, &str)]) -> ListParams {
+        ListParams::parse(&pairs(query)).unwrap()
+    }
+
+    fn errors(query: &[(&str, &str)]) -> Vec<String> {
+        match ListParams::parse(&pairs(query)) {
+            Ok(_) => panic!("the query should have been rejected"),
+            Err(errors) => errors,
+        }
+    }
+
+    #[test]
+    fn defaults_to_a_full_first_page_as_a_bare_array() {
+        let params = parse(&[]);
+        assert_eq!(params.query.limit, MAX_PAGE_SIZE);
+        assert_eq!(params.query.offset, 0);
+        assert!(params.query.after.is_none());
+        assert!(params.query.filter.is_empty());
+        assert!(params.query.sort.is_empty());
+        assert!(!params.envelope);
+    }
+
+    #[test]
+    fn limit_is_clamped_to_the_page_size_bounds() {
+        assert_eq!(parse(&[("limit", "0")]).query.limit, 1);
+        assert_eq!(parse(&[("limit", "25")]).query.limit, 25);
+        assert_eq!(parse(&[("limit", "100000")]).query.limit, MAX_PAGE_SIZE);
+    }
+
+    #[test]
+    fn offset_must_fit_the_databases() {
+        assert_eq!(parse(&[("offset", "9223372036854775807")]).query.offset, MAX_OFFSET);
+        assert_eq!(errors(&[("offset", "9223372036854775808")]), ["'offset' must be at most 9223372036854775807"]);
+    }
+
+    #[test]
+    fn cursor_paging_always_uses_the_envelope() {
+        let id = ObjectId::new();
+        let cursor = encode_cursor(&id);
+        let params = parse(&[("cursor", &cursor)]);
+        assert_eq!(params.query.after, Some(id));
+        assert!(params.envelope);
+    }
+
+    #[test]
+    fn cursor_cannot_be_combined_with_offset_or_sort() {
+        let cursor = encode_cursor(&ObjectId::new());
+        assert_eq!(
+            errors(&[("cursor", &cursor), ("offset", "10"), ("sort", "title")]),
+            ["'cursor' and 'offset' cannot be combined", "'cursor' cannot be combined with 'sort'"]
+        );
+    }
+
+    #[test]
+    fn sort_keys_keep_their_order_and_direction() {
+        let sort = parse(&[("sort", "-priority,due_at")]).query.sort;
+        assert_eq!(sort.len(), 2);
+        assert!(matches!(sort[0], SortKey { field: SortField::Priority, descending: true }));
+        assert!(matches!(sort[1], SortKey { field: SortField::DueAt, descending: false }));
+    }
+
+    #[test]
+    fn filters_are_parsed() {
+        let parent = ObjectId::new();
+        let filter = parse(&[
+            ("completed", "false"),
+            ("priority", "high,urgent"),
+            ("due_before", "2026-01-08T00:00:00Z"),
+            ("tag", "Backend,urgent"),
+            ("parent", &parent.to_hex()),
+        ])
+        .query
+        .filter;
+        assert_eq!(filter.completed, Some(false));
+        assert_eq!(filter.priorities, [Priority::High, Priority::Urgent]);
+        assert_eq!(filter.due_before, Some("2026-01-08T00:00:00Z".parse().unwrap()));
+        assert_eq!(filter.tags, ["backend", "urgent"]);
+        assert_eq!(filter.parent, Some(Some(parent)));
+        assert_eq!(parse(&[("parent", "none")]).query.filter.parent, Some(None));
+    }
+
+    #[test]
+    fn every_problem_is_reported() {
+        assert_eq!(
+            errors(&[("limit", "ten"), ("priority", "high,extreme"), ("sort", "color"), ("colour", "red")]),
+            [
+                "invalid value 'ten' for 'limit'",
+                "invalid value 'extreme' for 'priority'",
+                "invalid value 'color' for 'sort'",
+                "unknown query parameter 'colour'",
+            ]
+        );
+    }
+
+    #[test]
+    fn delete_filter_takes_only_filters() {
+        let filter = parse_filter(&pairs(&[("completed", "true")])).unwrap();
+        assert_eq!(filter.completed, Some(true));
+        assert_eq!(parse_filter(&pairs(&[("limit", "5")])).err().unwrap(), ["unknown query parameter 'limit'"]);
+    }
+
+    #[test]
+    fn delete_filter_requires_a_condition() {
+        assert_eq!(parse_filter(&[]).err().unwrap(), ["at least one filter is required"]);
+    }
+}
@@ -0,0 +1,267 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::Task;
+
+/// Relative weight of a match in each field. The Mongo text index is created
+/// with the same weights, so its ranking is close to the in-process one, but
+/// not the same: it also matches stemmed words and ignores stop words.
+pub const TITLE_WEIGHT: f64 = 2.0;
+pub const DESCRIPTION_WEIGHT: f64 = 1.0;
+
+/// Characters of context kept on each side of the first match in a snippet.
+const SNIPPET_CONTEXT: usize = 40;
+
+#[derive(Deserialize)]
+pub struct SearchParams {
+    pub q: String,
+    pub limit: Option<u64>,
+}
+
+pub struct SearchHit {
+    pub task: Task,
+    pub score: f64,
+}
+
+#[derive(Serialize)]
+pub struct SearchResult {
+    pub task: Task,
+    pub score: f64,
+    pub highlights: Highlights,
+}
+
+/// HTML snippets with matched words wrapped in `<mark>`; the rest of the text
+/// is escaped so the snippets can be inserted into a page as-is. Only exact
+/// (case-insensitive) matches are marked, so a field that Mongo matched on a
+/// stemmed form alone, such as "milks" for "milk", has no snippet.
+#[derive(Serialize)]
+pub struct Highlights {
+    pub title: Option<String>,
+    pub description: Option<String>,
+}
+
+impl SearchResult {
+    pub fn new(hit: SearchHit, terms: &[String]) -> Self {
+        let highlights = Highlights {
+            title: highlight(&hit.task.title, terms, None),
+            description: hit
+                .task
+                .description
+                .as_deref()
+                .and_then(|description| highlight(description, terms, Some(SNIPPET_CONTEXT))),
+        };
+        SearchResult { task: hit.task, score: hit.score, highlights }
+    }
+}
+
+/// Lowercased alphanumeric words of `text`, the unit both scoring and
+/// highlighting work on.
+pub fn tokenize(text: &str) -> Vec<String> {
+    words(text).into_iter().map(|(_, word)| word.to_lowercase()).collect()
+}
+
+/// Relevance of `task` for the query terms, or 0 when nothing matches.
+/// Matches in short fields count for more than matches in long ones.
+pub fn score(task: &Task, terms: &[String]) -> f64 {
+    let field_score = |text: &str, weight: f64| {
+        let tokens = tokenize(text);
+        let matches = tokens.iter().filter(|token| terms.contains(token)).count();
+        if matches == 0 {
+            0.0
+        } else {
+            weight * matches as f64 / (tokens.len() as f64).sqrt()
+        }
+    };
+
+    field_score(&task.title, TITLE_WEIGHT)
+        + task.description.as_deref().map_or(0.0, |description| field_score(description, DESCRIPTION_WEIGHT))
+}
+
+/// Ranks tasks on exact word matches, best match first. This approximates
+/// the Mongo text index for the backends that have none.
+pub fn rank<I>(tasks: I, terms: &[String], limit: u64) -> Vec<SearchHit>
+where
+    I: IntoIterator<Item = Task>,
+{
+    let mut hits: Vec<SearchHit> = tasks
+        .into_iter()
+        .map(|task| SearchHit { score: score(&task, terms), task })
+        .filter(|hit| hit.score > 0.0)
+        .collect();
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.task.id.cmp(&b.task.id)));
+    hits.truncate(limit as usize);
+    hits
+}
+
+/// Marks every matching word in `text`. With `context`, the result is cut down
+/// to that many characters around the first match.
+fn highlight(text: &str, terms: &[String], context: Option<usize>) -> Option<String> {
+    let matches: Vec<(usize, &str)> = words(text)
+        .into_iter()
+        .filter(|(_, word)| terms.contains(&word.to_lowercase()))
+        .collect();
+    let (first_match, _) = *matches.first()?;
+
+    let (start, end) = match context {
+        Some(context) => (
+            floor_char_boundary(text, first_match.saturating_sub(context)),
+            floor_char_boundary(text, first_match + context * 2),
+        ),
+        None => (0, text.len()),
+    };
+
+    let mut snippet = String::new();
+    if start > 0 {
+        snippet.push('…');
+    }
+    let mut position = start;
+    for (offset, word) in matches.into_iter().filter(|(offset, word)| *offset >= start && offset + word.len() <= end) {
+        snippet.push_str(&escape_html(&text[position..offset]));
+        snippet.push_str("<mark>");
+        snippet.push_str(&escape_html(word));
+        snippet.push_str("</mark>");
+        position = offset + word.len();
+    }
+    snippet.push_str(&escape_html(&text[position..end]));
+    if end < text.len() {
+        snippet.push('…');
+    }
+    Some(snippet)
+}
+
+/// Alphanumeric runs in `text` with their byte offsets.
+fn words(text: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (index, c) in text.char_indices() {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(index),
+            (false, Some(word_start)) => {
+                words.push((word_start, &text[word_start..index]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(word_start) = start {
+        words.push((word_start, &text[word_start..]));
+    }
+    words
+}
+
+fn floor_char_boundary(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use mongodb::bson::oid::ObjectId;
+
+    use super::*;
+    use crate::models::TaskInput;
+
+    fn task(title: &str, description: Option<&str>) -> Task {
+        let input = TaskInput {
+            title: title.to_string(),
+            description: description.map(str::to_string),
+            completed: false,
+            due_at: None,
+            priority: None,
+            tags: Vec::new(),
+            parent_id: None,
+        };
+        input.into_task(ObjectId::new(), ObjectId::new(), None, Utc::now())
+    }
+
+    fn terms(query: &str) -> Vec<String> {
+        tokenize(query)
+    }
+
+    #[test]
+    fn tokenize_lowercases_alphanumeric_words() {
+        assert_eq!(tokenize("Buy MILK, eggs & 2 loaves!"), ["buy", "milk", "eggs", "2", "loaves"]);
+    }
+
+    #[test]
+    fn score_is_zero_without_a_match() {
+        assert_eq!(score(&task("Buy milk", Some("at the store")), &terms("bread")), 0.0);
+    }
+
+    #[test]
+    fn title_matches_outweigh_description_matches() {
+        let in_title = score(&task("milk", None), &terms("milk"));
+        let in_description = score(&task("groceries", Some("milk")), &terms("milk"));
+        assert_eq!(in_title, TITLE_WEIGHT);
+        assert_eq!(in_description, DESCRIPTION_WEIGHT);
+    }
+
+    #[test]
+    fn matches_in_shorter_fields_score_higher() {
+        let short = score(&task("Buy milk", None), &terms("milk"));
+        let long = score(&task("Buy milk and bread and eggs", None), &terms("milk"));
+        assert!(short > long);
+    }
+
+    #[test]
+    fn rank_drops_misses_and_orders_by_score() {
+        let hits = rank(
+            vec![task("Buy bread and milk", None), task("Walk the dog", None), task("Milk", None)],
+            &terms("milk"),
+            10,
+        );
+        let titles: Vec<&str> = hits.iter().map(|hit| hit.task.title.as_str()).collect();
+        assert_eq!(titles, ["Milk", "Buy bread and milk"]);
+    }
+
+    #[test]
+    fn highlight_marks_every_match_case_insensitively() {
+        assert_eq!(
+            highlight("Milk and more milk", &terms("milk"), None).as_deref(),
+            Some("<mark>Milk</mark> and more <mark>milk</mark>")
+        );
+        assert_eq!(highlight("Buy bread", &terms("milk"), None), None);
+    }
+
+    #[test]
+    fn highlight_escapes_html() {
+        assert_eq!(
+            highlight("<b>milk</b> & \"eggs\"", &terms("milk"), None).as_deref(),
+            Some("&lt;b&gt;<mark>milk</mark>&lt;/b&gt; &amp; &quot;eggs&quot;")
+        );
+    }
+
+    #[test]
+    fn highlight_cuts_long_text_around_the_first_match() {
+        let text = format!("{} milk {}", "a".repeat(20), "b".repeat(20));
+        assert_eq!(
+            highlight(&text, &terms("milk"), Some(5)).as_deref(),
+            Some("…aaaa <mark>milk</mark> bbbbb…")
+        );
+    }
+
+    #[test]
+    fn highlight_keeps_snippets_on_char_boundaries() {
+        let text = "ééééé milk";
+        let snippet = highlight(text, &terms("milk"), Some(3)).unwrap();
+        assert!(snippet.ends_with("<mark>milk</mark>"));
+    }
+}
@@ -0,0 +1,528 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use crate::models::{ApiKey, ListRole, Priority, Progress, Role, Task, TaskChanges, TaskInput, TaskList, User};
+use crate::search::SearchHit;
+use crate::subtasks::MAX_SUBTASK_DEPTH;
+use crate::validation::MAX_TAGS;
+
+pub mod memory;
+pub mod mongo;
+pub mod sqlite;
+
+pub use memory::{
+    MemoryApiKeyRepository, MemoryIdempotencyRepository, MemoryListRepository, MemoryRefreshTokenRepository,
+    MemoryRevokedTokenRepository, MemoryTaskRepository, MemoryUserRepository,
+};
+pub use mongo::{
+    tenant_database, MongoApiKeyRepository, MongoIdempotencyRepository, MongoListRepository,
+    MongoRefreshTokenRepository, MongoRevokedTokenRepository, MongoTaskRepository, MongoUserRepository,
+    TenantCollection,
+};
+pub use sqlite::{
+    SqliteApiKeyRepository, SqliteDatabase, SqliteIdempotencyRepository, SqliteListRepository,
+    SqliteRefreshTokenRepository, SqliteRevokedTokenRepository, SqliteTaskRepository, SqliteUserRepository,
+};
+
+#[derive(Clone, Debug)]
+pub enum StorageError {
+    /// A write would violate a uniqueness constraint.
+    Conflict(String),
+    /// A conditional write found the task at a different revision.
+    PreconditionFailed,
+    /// A write would nest a task under a parent it cannot have; the message
+    /// says why.
+    InvalidParent(String),
+    Database(String),
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Conflict(message) => write!(f, "conflict: {}", message),
+            StorageError::PreconditionFailed => write!(f, "revision precondition failed"),
+            StorageError::InvalidParent(message) => write!(f, "invalid parent: {}", message),
+            StorageError::Database(message) => write!(f, "database error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+/// Fails unless the task is at one of the `expected` revisions; `None` means
+/// the write is unconditional.
+pub fn check_revision(task: &Task, expected: Option<&[i64]>) -> Result<(), StorageError> {
+    match expected {
+        Some(revisions) if !revisions.contains(&task.revision) => Err(StorageError::PreconditionFailed),
+        _ => Ok(()),
+    }
+}
+
+pub fn no_owner_left() -> StorageError {
+    StorageError::Conflict("A list must keep at least one owner".to_string())
+}
+
+pub fn too_many_tags() -> StorageError {
+    StorageError::Conflict(format!("A task can carry at most {} tags", MAX_TAGS))
+}
+
+pub fn nested_too_deep() -> StorageError {
+    StorageError::InvalidParent(format!("must not nest subtasks more than {} levels deep", MAX_SUBTASK_DEPTH))
+}
+
+/// Fails unless task `id` can be moved under a new parent. `ancestors` are
+/// that parent and the tasks above it, in any order, and `height` is how many
+/// levels of subtasks the task has below it. Backends may stop collecting
+/// either once it is past [`MAX_SUBTASK_DEPTH`].
+pub fn check_nesting(id: ObjectId, ancestors: &[ObjectId], height: usize) -> Result<(), StorageError> {
+    if ancestors.contains(&id) {
+        return Err(StorageError::InvalidParent("must not be the task itself or one of its subtasks".to_string()));
+    }
+    if ancestors.len() + height > MAX_SUBTASK_DEPTH {
+        return Err(nested_too_deep());
+    }
+    Ok(())
+}
+
+/// Fails unless the task has room for those of `tags` it does not carry yet.
+pub fn check_tag_limit(task: &Task, tags: &[String]) -> Result<(), StorageError> {
+    let added = tags.iter().filter(|tag| !task.tags.contains(tag)).count();
+    if task.tags.len() + added > MAX_TAGS {
+        return Err(too_many_tags());
+    }
+    Ok(())
+}
+
+/// Conditions a task must meet to be listed; unset fields match everything.
+#[derive(Default)]
+pub struct TaskFilter {
+    pub completed: Option<bool>,
+    /// Matches any of the given priorities.
+    pub priorities: Vec<Priority>,
+    /// Tasks without a due date never match a due date bound.
+    pub due_before: Option<DateTime<Utc>>,
+    pub due_after: Option<DateTime<Utc>>,
+    /// Matches tasks carrying all of the given tags.
+    pub tags: Vec<String>,
+    /// Matches the subtasks of the given task, or with `Some(None)` the tasks
+    /// that are not a subtask.
+    pub parent: Option<Option<ObjectId>>,
+}
+
+impl TaskFilter {
+    /// True when the filter places no condition at all.
+    pub fn is_empty(&self) -> bool {
+        self.completed.is_none()
+            && self.priorities.is_empty()
+            && self.due_before.is_none()
+            && self.due_after.is_none()
+            && self.tags.is_empty()
+            && self.parent.is_none()
+    }
+
+    pub fn matches(&self, task: &Task) -> bool {
+        self.completed.is_none_or(|completed| task.completed == completed)
+            && (self.priorities.is_empty() || task.priority.is_some_and(|p| self.priorities.contains(&p)))
+            && self.due_before.is_none_or(|before| task.due_at.is_some_and(|due| due < before))
+            && self.due_after.is_none_or(|after| task.due_at.is_some_and(|due| due > after))
+            && self.tags.iter().all(|tag| task.tags.contains(tag))
+            && self.parent.is_none_or(|parent| task.parent_id == parent.map(|parent| parent.to_hex()))
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum SortField {
+    Title,
+    Priority,
+    DueAt,
+    CreatedAt,
+    UpdatedAt,
+    Completed,
+}
+
+impl FromStr for SortField {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "title" => Ok(SortField::Title),
+            "priority" => Ok(SortField::Priority),
+            "due_at" => Ok(SortField::DueAt),
+            "created_at" => Ok(SortField::CreatedAt),
+            "updated_at" => Ok(SortField::UpdatedAt),
+            "completed" => Ok(SortField::Completed),
+            other => Err(format!("unknown sort field '{}'", other)),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct SortKey {
+    pub field: SortField,
+    pub descending: bool,
+}
+
+/// Orders tasks the way the database backends do: missing values first in
+/// ascending order, priorities by rank, and `_id` as the final tie-breaker.
+pub fn compare_tasks(sort: &[SortKey], a: &Task, b: &Task) -> Ordering {
+    sort.iter()
+        .map(|key| {
+            let ordering = match key.field {
+                SortField::Title => a.title.cmp(&b.title),
+                SortField::Priority => a.priority.cmp(&b.priority),
+                SortField::DueAt => a.due_at.cmp(&b.due_at),
+                SortField::CreatedAt => a.created_at.cmp(&b.created_at),
+                SortField::UpdatedAt => a.updated_at.cmp(&b.updated_at),
+                SortField::Completed => a.completed.cmp(&b.completed),
+            };
+            if key.descending { ordering.reverse() } else { ordering }
+        })
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or_else(|| a.id.cmp(&b.id))
+}
+
+/// A page of tasks, in `_id` order unless `sort` says otherwise.
+pub struct TaskQuery {
+    pub limit: u64,
+    pub offset: u64,
+    /// Only return tasks whose id sorts after this one (cursor paging).
+    /// Never combined with `sort`.
+    pub after: Option<ObjectId>,
+    pub filter: TaskFilter,
+    pub sort: Vec<SortKey>,
+}
+
+pub struct TaskPage {
+    pub tasks: Vec<Task>,
+    /// Number of tasks matching the filter, regardless of paging.
+    pub total: u64,
+    pub has_more: bool,
+}
+
+impl TaskPage {
+    /// Builds a page from up to `limit + 1` fetched tasks; the extra one only
+    /// signals that another page exists.
+    pub fn from_overfetch(mut tasks: Vec<Task>, query: &TaskQuery, total: u64) -> Self {
+        let has_more = tasks.len() as u64 > query.limit;
+        tasks.truncate(query.limit as usize);
+        TaskPage { tasks, total, has_more }
+    }
+}
+
+/// The tasks an operation may see: the personal tasks of `owner`, which are
+/// in no list, and every task in one of `lists`, whoever created it. With
+/// `unowned`, also the personal tasks from before accounts existed, which
+/// have no owner.
+#[derive(Clone, Debug, Default)]
+pub struct TaskScope {
+    pub owner: Option<ObjectId>,
+    pub lists: Vec<ObjectId>,
+    pub unowned: bool,
+}
+
+impl TaskScope {
+    pub fn personal(owner: ObjectId) -> Self {
+        TaskScope { owner: Some(owner), lists: Vec::new(), unowned: false }
+    }
+
+    pub fn list(id: ObjectId) -> Self {
+        TaskScope { owner: None, lists: vec![id], unowned: false }
+    }
+
+    /// Only the tasks without an owner, for admins to hand over.
+    pub fn unowned() -> Self {
+        TaskScope { owner: None, lists: Vec::new(), unowned: true }
+    }
+
+    pub fn contains(&self, task: &Task) -> bool {
+        match task.list_id.as_deref() {
+            Some(list_id) => self.lists.iter().any(|list| list.to_hex() == list_id),
+            None if task.owner_id.is_none() => self.unowned,
+            None => self.owner.is_some_and(|owner| task.is_owned_by(&owner)),
+        }
+    }
+}
+
+/// A tag and the number of tasks carrying it.
+#[derive(Serialize)]
+pub struct TagCount {
+    pub name: String,
+    pub count: u64,
+}
+
+/// Orders tag counts most used first, then by name.
+pub fn sort_tag_counts(counts: &mut [TagCount]) {
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+}
+
+/// Persistence operations the task handlers rely on.
+///
+/// Handlers only ever talk to this trait, so the backing store can be swapped
+/// without touching the HTTP layer. Every operation but
+/// [`TaskRepository::transfer`] is confined to the tasks in its [`TaskScope`];
+/// other tasks behave as if they did not exist.
+#[async_trait]
+pub trait TaskRepository: Send + Sync {
+    async fn list(&self, scope: &TaskScope, query: &TaskQuery) -> Result<TaskPage, StorageError>;
+
+    /// Tasks whose title or description contains any of the (lowercased)
+    /// terms, best match first. MongoDB ranks by its text index, which also
+    /// matches stemmed forms; the other backends use [`crate::search::rank`].
+    async fn search(&self, scope: &TaskScope, terms: &[String], limit: u64) -> Result<Vec<SearchHit>, StorageError>;
+
+    async fn get(&self, scope: &TaskScope, id: ObjectId) -> Result<Option<Task>, StorageError>;
+
+    /// Creates a task owned by `owner`, in `list` or as a personal task.
+    async fn create(&self, owner: ObjectId, list: Option<ObjectId>, input: TaskInput) -> Result<Task, StorageError>;
+
+    /// Creates several tasks in one round trip, returning the outcome of each
+    /// in input order. On error the memory and SQLite backends store none of
+    /// them; MongoDB keeps the ones before the failing insert, which are
+    /// reported as created.
+    async fn create_many(
+        &self,
+        owner: ObjectId,
+        list: Option<ObjectId>,
+        inputs: Vec<TaskInput>,
+    ) -> Vec<Result<Task, StorageError>>;
+
+    /// Applies `changes` as [`Task::apply`] does, returning the updated task,
+    /// or `None` when no task has the given id. Every write bumps `updated_at`
+    /// and `revision`.
+    ///
+    /// With `expected_revisions`, the write only happens if the task is at one
+    /// of those revisions and fails with [`StorageError::PreconditionFailed`]
+    /// otherwise, checked atomically with the write.
+    ///
+    /// A new parent is checked with [`check_nesting`] as part of the write, so
+    /// that concurrent moves cannot form a cycle or nest too deep together.
+    /// MongoDB, which cannot check other tasks in the same write, checks again
+    /// right after it and puts the previous parent back if another move got in
+    /// between.
+    async fn update(
+        &self,
+        scope: &TaskScope,
+        id: ObjectId,
+        changes: TaskChanges,
+        expected_revisions: Option<Vec<i64>>,
+    ) -> Result<Option<Task>, StorageError>;
+
+    /// Returns `false` when no task has the given id. `expected_revisions`
+    /// works as for [`TaskRepository::update`]. Subtasks of a deleted task
+    /// are detached from it, which counts as a write to them.
+    async fn delete(
+        &self,
+        scope: &TaskScope,
+        id: ObjectId,
+        expected_revisions: Option<Vec<i64>>,
+    ) -> Result<bool, StorageError>;
+
+    /// Deletes every task matching `filter`, returning how many were removed.
+    /// Their subtasks are detached as with [`TaskRepository::delete`].
+    async fn delete_matching(&self, scope: &TaskScope, filter: &TaskFilter) -> Result<u64, StorageError>;
+
+    /// Adds `tags` to those of the task without rewriting the others, so that
+    /// concurrent tag changes do not undo each other. Fails with
+    /// [`StorageError::Conflict`] instead of going over [`MAX_TAGS`]; `None`
+    /// when no task has the given id. Bumps `updated_at` and `revision`.
+    async fn add_tags(&self, scope: &TaskScope, id: ObjectId, tags: &[String]) -> Result<Option<Task>, StorageError>;
+
+    /// Removes `tags` from the task, like [`TaskRepository::add_tags`] does
+    /// for adding them.
+    async fn remove_tags(
+        &self,
+        scope: &TaskScope,
+        id: ObjectId,
+        tags: &[String],
+    ) -> Result<Option<Task>, StorageError>;
+
+    /// Every tag used by the tasks in `scope`, as ordered by [`sort_tag_counts`].
+    async fn tag_counts(&self, scope: &TaskScope) -> Result<Vec<TagCount>, StorageError>;
+
+    /// Replaces the tags in `from` by `to` on every task in `scope`, as
+    /// [`Task::rename_tags`] does, returning how many tasks changed. Renaming
+    /// a tag to one a task already carries merges the two.
+    async fn rename_tags(&self, scope: &TaskScope, from: &[String], to: &str) -> Result<u64, StorageError>;
+
+    /// Completed and total direct subtasks of each of `parents`, by parent
+    /// id. Subtasks share the list of their parent, so no scope applies.
+    /// Parents without subtasks are left out.
+    async fn subtask_progress(&self, parents: &[ObjectId]) -> Result<HashMap<String, Progress>, StorageError>;
+
+    /// Hands the task and its subtasks, however deeply nested, over to
+    /// `new_owner` in one write, whoever owned them before, including tasks
+    /// that predate accounts and have no owner. A transferred subtask is
+    /// detached from its parent, which stays with the previous owner. Bumps
+    /// `updated_at` and `revision` like any other write; `None` when no task
+    /// has the given id.
+    async fn transfer(&self, id: ObjectId, new_owner: ObjectId) -> Result<Option<Task>, StorageError>;
+}
+
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    /// Fails with [`StorageError::Conflict`] when the email is already taken.
+    async fn create(&self, user: &User) -> Result<(), StorageError>;
+
+    async fn get(&self, id: ObjectId) -> Result<Option<User>, StorageError>;
+
+    /// Looks a user up by their (lowercased) email.
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, StorageError>;
+
+    /// Users in registration order, skipping the first `offset`.
+    async fn list(&self, offset: u64, limit: u64) -> Result<UserPage, StorageError>;
+
+    /// Returns the updated user, or `None` when no user has the given id.
+    async fn set_role(&self, id: ObjectId, role: Role) -> Result<Option<User>, StorageError>;
+
+    /// Disables the account as of `disabled_at`, or enables it again with
+    /// `None`. Returns the updated user, or `None` when no user has the id.
+    async fn set_disabled(
+        &self,
+        id: ObjectId,
+        disabled_at: Option<DateTime<Utc>>,
+    ) -> Result<Option<User>, StorageError>;
+}
+
+pub struct UserPage {
+    pub users: Vec<User>,
+    /// Number of users in total, not just on this page.
+    pub total: u64,
+}
+
+#[async_trait]
+pub trait ListRepository: Send + Sync {
+    async fn create(&self, list: &TaskList) -> Result<(), StorageError>;
+
+    async fn get(&self, id: ObjectId) -> Result<Option<TaskList>, StorageError>;
+
+    /// Lists `user_id` is a member of, oldest first.
+    async fn for_member(&self, user_id: ObjectId) -> Result<Vec<TaskList>, StorageError>;
+
+    /// Returns the renamed list, or `None` when no list has the id.
+    async fn rename(&self, id: ObjectId, name: &str) -> Result<Option<TaskList>, StorageError>;
+
+    /// Adds `user_id` to the list, or changes their role if already a member.
+    /// Fails with [`StorageError::Conflict`] rather than demote the last owner.
+    async fn set_member(
+        &self,
+        id: ObjectId,
+        user_id: ObjectId,
+        role: ListRole,
+    ) -> Result<Option<TaskList>, StorageError>;
+
+    /// Fails with [`StorageError::Conflict`] rather than remove the last owner.
+    async fn remove_member(&self, id: ObjectId, user_id: ObjectId) -> Result<Option<TaskList>, StorageError>;
+
+    /// Deletes the list together with its tasks. The memory and SQLite
+    /// backends do both at once; MongoDB deletes the list first, so that its
+    /// tasks are out of everyone's reach even if deleting them then fails.
+    async fn delete(&self, id: ObjectId) -> Result<bool, StorageError>;
+}
+
+/// A refresh token; only its hash is stored. Each login starts a session, and
+/// the tokens that replace one another on refresh all belong to it, so the
+/// whole session can be revoked at once.
+#[derive(Clone)]
+pub struct RefreshToken {
+    pub token_hash: String,
+    pub session_id: ObjectId,
+    pub user_id: ObjectId,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+pub enum Rotation {
+    /// The token was current and has been replaced by this one.
+    Rotated(RefreshToken),
+    /// The token had already been replaced, so someone else may hold a copy;
+    /// its whole session has been revoked.
+    Reused,
+    /// Unknown, expired or revoked token.
+    Invalid,
+}
+
+#[async_trait]
+pub trait RefreshTokenRepository: Send + Sync {
+    async fn create(&self, token: &RefreshToken) -> Result<(), StorageError>;
+
+    /// Retires the token with `token_hash` and stores `replacement_hash` in its
+    /// place, in the same session and valid until `expires_at`. Retired tokens
+    /// are kept until they expire so that their reuse can be detected.
+    async fn rotate(
+        &self,
+        token_hash: &str,
+        replacement_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Rotation, StorageError>;
+
+    async fn revoke_session(&self, session_id: ObjectId) -> Result<(), StorageError>;
+
+    /// Revokes every session of `user_id`.
+    async fn revoke_user(&self, user_id: ObjectId) -> Result<(), StorageError>;
+}
+
+/// Access tokens revoked before they expire, by their `jti` claim.
+#[async_trait]
+pub trait RevokedTokenRepository: Send + Sync {
+    /// Entries may be dropped after `expires_at`, when the token is rejected
+    /// as expired anyway.
+    async fn revoke(&self, jti: &str, expires_at: DateTime<Utc>) -> Result<(), StorageError>;
+
+    async fn is_revoked(&self, jti: &str) -> Result<bool, StorageError>;
+}
+
+#[async_trait]
+pub trait ApiKeyRepository: Send + Sync {
+    async fn create(&self, key: &ApiKey) -> Result<(), StorageError>;
+
+    /// Keys of `user_id`, oldest first.
+    async fn list(&self, user_id: ObjectId) -> Result<Vec<ApiKey>, StorageError>;
+
+    async fn find_by_hash(&self, key_hash: &str) -> Result<Option<ApiKey>, StorageError>;
+
+    /// Records that the key was used at `at`.
+    async fn touch(&self, id: ObjectId, at: DateTime<Utc>) -> Result<(), StorageError>;
+
+    /// Deletes the key if it belongs to `user_id`, returning whether it did.
+    async fn revoke(&self, user_id: ObjectId, id: ObjectId) -> Result<bool, StorageError>;
+}
+
+/// A response recorded under an idempotency key and replayed verbatim when the
+/// request is retried.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct StoredResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    /// JSON response body.
+    pub body: String,
+}
+
+/// What an earlier request left under an idempotency key.
+pub struct IdempotencyRecord {
+    pub request_hash: String,
+    /// `None` while that request is still being processed.
+    pub response: Option<StoredResponse>,
+}
+
+/// Idempotency keys seen recently. Every backend forgets a key once its TTL,
+/// counted from when it was claimed, has passed.
+#[async_trait]
+pub trait IdempotencyRepository: Send + Sync {
+    /// Claims `key` for a request with the given hash. Returns `None` when the
+    /// key was free, or the record of the earlier request otherwise, leaving
+    /// it untouched.
+    async fn claim(&self, key: &str, request_hash: &str) -> Result<Option<IdempotencyRecord>, StorageError>;
+
+    /// Stores the response of the request that claimed `key`.
+    async fn complete(&self, key: &str, response: &StoredResponse) -> Result<(), StorageError>;
+
+    /// Frees `key` after its request failed, so that a retry runs again.
+    async fn release(&self, key: &str) -> Result<(), StorageError>;
+}
@@ -0,0 +1,402 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Bound;
+use std::sync::RwLock;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use mongodb::bson::oid::ObjectId;
+
+use crate::models::{Progress, Task, TaskChanges, TaskInput};
+use crate::search::{self, SearchHit};
+use crate::subtasks::MAX_SUBTASK_DEPTH;
+use super::{
+    check_nesting, check_revision, check_tag_limit, compare_tasks, sort_tag_counts, StorageError, TagCount, TaskFilter,
+    TaskPage, TaskQuery, TaskRepository, TaskScope,
+};
+
+mod api_keys;
+mod idempotency;
+mod lists;
+mod refresh_tokens;
+mod revoked_tokens;
+mod users;
+
+pub use api_keys::MemoryApiKeyRepository;
+pub use idempotency::MemoryIdempotencyRepository;
+pub use lists::MemoryListRepository;
+pub use refresh_tokens::MemoryRefreshTokenRepository;
+pub use revoked_tokens::MemoryRevokedTokenRepository;
+pub use users::MemoryUserRepository;
+
+/// Process-local task store for development and CI, where no MongoDB is
+/// available. Ids are generated as ObjectIds so clients see the same id format
+/// as with the Mongo backend, and the map keeps them in insertion order.
+#[derive(Default)]
+pub struct MemoryTaskRepository {
+    tasks: RwLock<BTreeMap<ObjectId, Task>>,
+}
+
+impl MemoryTaskRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies `change` to the task under the write lock and records the write,
+    /// unless `change` fails. `change` also gets to see all tasks as they are
+    /// before the write.
+    fn modify<F>(
+        &self,
+        scope: &TaskScope,
+        id: ObjectId,
+        expected_revisions: Option<&[i64]>,
+        change: F,
+    ) -> Result<Option<Task>, StorageError>
+    where
+        F: FnOnce(&BTreeMap<ObjectId, Task>, &mut Task) -> Result<(), StorageError>,
+    {
+        let mut tasks = self.tasks.write().unwrap();
+        let Some(existing) = tasks.get(&id).filter(|task| scope.contains(task)) else {
+            return Ok(None);
+        };
+        check_revision(existing, expected_revisions)?;
+        let mut task = existing.clone();
+        change(&tasks, &mut task)?;
+        task.updated_at = Utc::now();
+        task.revision += 1;
+        tasks.insert(id, task.clone());
+        Ok(Some(task))
+    }
+}
+
+/// `parent` and the tasks above it, no more than one past the deepest nesting
+/// allowed.
+fn ancestors(tasks: &BTreeMap<ObjectId, Task>, parent: ObjectId) -> Vec<ObjectId> {
+    let mut ancestors = Vec::new();
+    let mut next = Some(parent);
+    while let Some(id) = next.filter(|id| ancestors.len() <= MAX_SUBTASK_DEPTH && !ancestors.contains(id)) {
+        ancestors.push(id);
+        next = tasks.get(&id).and_then(|task| task.parent_id.as_deref()).and_then(|id| ObjectId::parse_str(id).ok());
+    }
+    ancestors
+}
+
+/// Levels of subtasks below task `id`, counted no further than one past the
+/// deepest nesting allowed.
+fn height(tasks: &BTreeMap<ObjectId, Task>, id: ObjectId) -> usize {
+    let mut level = HashSet::from([id.to_hex()]);
+    let mut height = 0;
+    while height <= MAX_SUBTASK_DEPTH {
+        level = tasks
+            .iter()
+            .filter(|(_, task)| task.parent_id.as_ref().is_some_and(|parent| level.contains(parent)))
+            .map(|(child, _)| child.to_hex())
+            .collect();
+        if level.is_empty() {
+            break;
+        }
+        height += 1;
+    }
+    height
+}
+
+/// Detaches the subtasks of the `deleted` tasks, recording the write.
+fn detach_subtasks(tasks: &mut BTreeMap<ObjectId, Task>, deleted: &[ObjectId]) {
+    let deleted: HashSet<String> = deleted.iter().map(|id| id.to_hex()).collect();
+    let now = Utc::now();
+    for task in tasks.values_mut() {
+        if task.parent_id.as_ref().is_some_and(|parent| deleted.contains(parent)) {
+            task.parent_id = None;
+            task.updated_at = now;
+            task.revision += 1;
+        }
+    }
+}
+
+#[async_trait]
+impl TaskRepository for MemoryTaskRepository {
+    async fn list(&self, scope: &TaskScope, query: &TaskQuery) -> Result<TaskPage, StorageError> {
+        let tasks = self.tasks.read().unwrap();
+        let start = match query.after {
+            Some(after) => Bound::Excluded(after),
+            None => Bound::Unbounded,
+        };
+        let visible = |task: &&Task| scope.contains(task) && query.filter.matches(task);
+        let total = tasks.values().filter(visible).count();
+        let mut matching: Vec<Task> = tasks
+            .range((start, Bound::Unbounded))
+            .map(|(_, task)| task)
+            .filter(visible)
+            .cloned()
+            .collect();
+        if !query.sort.is_empty() {
+            matching.sort_by(|a, b| compare_tasks(&query.sort, a, b));
+        }
+        let page = matching
+            .into_iter()
+            .skip(query.offset as usize)
+            .take(query.limit as usize + 1)
+            .collect();
+        Ok(TaskPage::from_overfetch(page, query, total as u64))
+    }
+
+    async fn search(&self, scope: &TaskScope, terms: &[String], limit: u64) -> Result<Vec<SearchHit>, StorageError> {
+        let tasks = self.tasks.read().unwrap();
+        let visible = tasks.values().filter(|task| scope.contains(task)).cloned();
+        Ok(search::rank(visible, terms, limit))
+    }
+
+    async fn get(&self, scope: &TaskScope, id: ObjectId) -> Result<Option<Task>, StorageError> {
+        let tasks = self.tasks.read().unwrap();
+        Ok(tasks.get(&id).filter(|task| scope.contains(task)).cloned())
+    }
+
+    async fn create(&self, owner: ObjectId, list: Option<ObjectId>, input: TaskInput) -> Result<Task, StorageError> {
+        let id = ObjectId::new();
+        let task = input.into_task(id, owner, list, Utc::now());
+        self.tasks.write().unwrap().insert(id, task.clone());
+        Ok(task)
+    }
+
+    async fn create_many(
+        &self,
+        owner: ObjectId,
+        list: Option<ObjectId>,
+        inputs: Vec<TaskInput>,
+    ) -> Vec<Result<Task, StorageError>> {
+        let now = Utc::now();
+        let mut tasks = self.tasks.write().unwrap();
+        inputs
+            .into_iter()
+            .map(|input| {
+                let id = ObjectId::new();
+                let task = input.into_task(id, owner, list, now);
+                tasks.insert(id, task.clone());
+                Ok(task)
+            })
+            .collect()
+    }
+
+    async fn update(
+        &self,
+        scope: &TaskScope,
+        id: ObjectId,
+        changes: TaskChanges,
+        expected_revisions: Option<Vec<i64>>,
+    ) -> Result<Option<Task>, StorageError> {
+        self.modify(scope, id, expected_revisions.as_deref(), |tasks, existing| {
+            if let Some(Some(parent)) = changes.parent_id {
+                check_nesting(id, &ancestors(tasks, parent), height(tasks, id))?;
+            }
+            existing.apply(changes, Utc::now());
+            Ok(())
+        })
+    }
+
+    async fn delete(
+        &self,
+        scope: &TaskScope,
+        id: ObjectId,
+        expected_revisions: Option<Vec<i64>>,
+    ) -> Result<bool, StorageError> {
+        let mut tasks = self.tasks.write().unwrap();
+        let Some(existing) = tasks.get(&id).filter(|task| scope.contains(task)) else {
+            return Ok(false);
+        };
+        check_revision(existing, expected_revisions.as_deref())?;
+        tasks.remove(&id);
+        detach_subtasks(&mut tasks, &[id]);
+        Ok(true)
+    }
+
+    async fn delete_matching(&self, scope: &TaskScope, filter: &TaskFilter) -> Result<u64, StorageError> {
+        let mut tasks = self.tasks.write().unwrap();
+        let deleted: Vec<ObjectId> = tasks
+            .iter()
+            .filter(|(_, task)| scope.contains(task) && filter.matches(task))
+            .map(|(id, _)| *id)
+            .collect();
+        for id in &deleted {
+            tasks.remove(id);
+        }
+        detach_subtasks(&mut tasks, &deleted);
+        Ok(deleted.len() as u64)
+    }
+
+    async fn add_tags(&self, scope: &TaskScope, id: ObjectId, tags: &[String]) -> Result<Option<Task>, StorageError> {
+        self.modify(scope, id, None, |_, existing| {
+            check_tag_limit(existing, tags)?;
+            existing.add_tags(tags);
+            Ok(())
+        })
+    }
+
+    async fn remove_tags(
+        &self,
+        scope: &TaskScope,
+        id: ObjectId,
+        tags: &[String],
+    ) -> Result<Option<Task>, StorageError> {
+        self.modify(scope, id, None, |_, existing| {
+            existing.remove_tags(tags);
+            Ok(())
+        })
+    }
+
+    async fn tag_counts(&self, scope: &TaskScope) -> Result<Vec<TagCount>, StorageError> {
+        let tasks = self.tasks.read().unwrap();
+        let mut counts: HashMap<&str, u64> = HashMap::new();
+        for task in tasks.values().filter(|task| scope.contains(task)) {
+            for tag in &task.tags {
+                *counts.entry(tag).or_default() += 1;
+            }
+        }
+        let mut counts: Vec<TagCount> =
+            counts.into_iter().map(|(name, count)| TagCount { name: name.to_string(), count }).collect();
+        sort_tag_counts(&mut counts);
+        Ok(counts)
+    }
+
+    async fn rename_tags(&self, scope: &TaskScope, from: &[String], to: &str) -> Result<u64, StorageError> {
+        let mut tasks = self.tasks.write().unwrap();
+        let now = Utc::now();
+        let mut renamed = 0;
+        for task in tasks.values_mut().filter(|task| scope.contains(task)) {
+            if task.rename_tags(from, to) {
+                task.updated_at = now;
+                task.revision += 1;
+                renamed += 1;
+            }
+        }
+        Ok(renamed)
+    }
+
+    async fn subtask_progress(&self, parents: &[ObjectId]) -> Result<HashMap<String, Progress>, StorageError> {
+        let parents: HashSet<String> = parents.iter().map(|parent| parent.to_hex()).collect();
+        let tasks = self.tasks.read().unwrap();
+        let mut progress: HashMap<String, Progress> = HashMap::new();
+        for task in tasks.values() {
+            if let Some(parent) = task.parent_id.as_ref().filter(|parent| parents.contains(*parent)) {
+                let entry = progress.entry(parent.clone()).or_default();
+                entry.total += 1;
+                entry.done += task.completed as u64;
+            }
+        }
+        Ok(progress)
+    }
+
+    async fn transfer(&self, id: ObjectId, new_owner: ObjectId) -> Result<Option<Task>, StorageError> {
+        let mut tasks = self.tasks.write().unwrap();
+        if !tasks.contains_key(&id) {
+            return Ok(None);
+        }
+        let mut moved = vec![id];
+        let mut visited = HashSet::from([id]);
+        let mut next = 0;
+        while next < moved.len() {
+            let parent = moved[next].to_hex();
+            let children = tasks.iter().filter(|(_, task)| task.parent_id.as_ref() == Some(&parent));
+            for (child, _) in children {
+                if visited.insert(*child) {
+                    moved.push(*child);
+                }
+            }
+            next += 1;
+        }
+
+        let now = Utc::now();
+        for moved in &moved {
+            if let Some(task) = tasks.get_mut(moved) {
+                task.owner_id = Some(new_owner.to_hex());
+                task.updated_at = now;
+                task.revision += 1;
+                if *moved == id {
+                    task.parent_id = None;
+                }
+            }
+        }
+        Ok(tasks.get(&id).cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(title: &str, parent: Option<&Task>) -> TaskInput {
+        TaskInput {
+            title: title.to_string(),
+            description: None,
+            completed: false,
+            due_at: None,
+            priority: None,
+            tags: Vec::new(),
+            parent_id: parent.and_then(|parent| parent.id.as_deref()).map(|id| ObjectId::parse_str(id).unwrap()),
+        }
+    }
+
+    fn id(task: &Task) -> ObjectId {
+        ObjectId::parse_str(task.id.as_deref().unwrap()).unwrap()
+    }
+
+    /// `length` tasks, each a subtask of the one before.
+    async fn chain(repo: &MemoryTaskRepository, owner: ObjectId, length: usize) -> Vec<ObjectId> {
+        let mut chain: Vec<Task> = Vec::new();
+        for _ in 0..length {
+            let task = repo.create(owner, None, input("task", chain.last())).await.unwrap();
+            chain.push(task);
+        }
+        chain.iter().map(id).collect()
+    }
+
+    fn reparent(parent: ObjectId) -> TaskChanges {
+        TaskChanges { parent_id: Some(Some(parent)), ..TaskChanges::default() }
+    }
+
+    #[actix_web::test]
+    async fn update_counts_the_moved_subtasks_toward_the_depth_limit() {
+        let repo = MemoryTaskRepository::new();
+        let owner = ObjectId::new();
+        let scope = TaskScope::personal(owner);
+        let parents = chain(&repo, owner, 4).await;
+        let moved = chain(&repo, owner, 3).await;
+
+        let result = repo.update(&scope, moved[0], reparent(parents[3]), None).await;
+        assert!(matches!(result, Err(StorageError::InvalidParent(_))));
+        let task = repo.update(&scope, moved[0], reparent(parents[2]), None).await.unwrap().unwrap();
+        assert_eq!(task.parent_id, Some(parents[2].to_hex()));
+    }
+
+    #[actix_web::test]
+    async fn update_rejects_a_parent_below_the_task() {
+        let repo = MemoryTaskRepository::new();
+        let owner = ObjectId::new();
+        let scope = TaskScope::personal(owner);
+        let tasks = chain(&repo, owner, 3).await;
+
+        for parent in [tasks[0], tasks[2]] {
+            let result = repo.update(&scope, tasks[0], reparent(parent), None).await;
+            assert!(matches!(result, Err(StorageError::InvalidParent(_))));
+        }
+        assert_eq!(repo.get(&scope, tasks[0]).await.unwrap().unwrap().parent_id, None);
+    }
+
+    #[actix_web::test]
+    async fn transfer_detaches_a_subtask_and_moves_its_own_subtasks() {
+        let repo = MemoryTaskRepository::new();
+        let (old_owner, new_owner) = (ObjectId::new(), ObjectId::new());
+        let parent = repo.create(old_owner, None, input("parent", None)).await.unwrap();
+        let child = repo.create(old_owner, None, input("child", Some(&parent))).await.unwrap();
+        let grandchild = repo.create(old_owner, None, input("grandchild", Some(&child))).await.unwrap();
+
+        let moved = repo.transfer(id(&child), new_owner).await.unwrap().unwrap();
+        assert_eq!(moved.owner_id, Some(new_owner.to_hex()));
+        assert_eq!(moved.parent_id, None);
+
+        let scope = TaskScope::personal(new_owner);
+        let grandchild = repo.get(&scope, id(&grandchild)).await.unwrap().unwrap();
+        assert_eq!(grandchild.parent_id, child.id);
+        let parent = repo.get(&TaskScope::personal(old_owner), id(&parent)).await.unwrap().unwrap();
+        assert_eq!(parent.owner_id, Some(old_owner.to_hex()));
+        assert!(repo.subtask_progress(&[id(&parent)]).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+
+use crate::models::ApiKey;
+use crate::storage::{ApiKeyRepository, StorageError};
+
+/// Process-local API keys, keyed by key hash.
+#[derive(Default)]
+pub struct MemoryApiKeyRepository {
+    keys: RwLock<HashMap<String, ApiKey>>,
+}
+
+impl MemoryApiKeyRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl ApiKeyRepository for MemoryApiKeyRepository {
+    async fn create(&self, key: &ApiKey) -> Result<(), StorageError> {
+        self.keys.write().unwrap().insert(key.key_hash.clone(), key.clone());
+        Ok(())
+    }
+
+    async fn list(&self, user_id: ObjectId) -> Result<Vec<ApiKey>, StorageError> {
+        let keys = self.keys.read().unwrap();
+        let mut owned: Vec<ApiKey> = keys.values().filter(|key| key.user_id == user_id).cloned().collect();
+        owned.sort_by_key(|key| key.id);
+        Ok(owned)
+    }
+
+    async fn find_by_hash(&self, key_hash: &str) -> Result<Option<ApiKey>, StorageError> {
+        Ok(self.keys.read().unwrap().get(key_hash).cloned())
+    }
+
+    async fn touch(&self, id: ObjectId, at: DateTime<Utc>) -> Result<(), StorageError> {
+        let mut keys = self.keys.write().unwrap();
+        if let Some(key) = keys.values_mut().find(|key| key.id == id) {
+            key.last_used_at = Some(at);
+        }
+        Ok(())
+    }
+
+    async fn revoke(&self, user_id: ObjectId, id: ObjectId) -> Result<bool, StorageError> {
+        let mut keys = self.keys.write().unwrap();
+        let before = keys.len();
+        keys.retain(|_, key| !(key.id == id && key.user_id == user_id));
+        Ok(keys.len() < before)
+    }
+}
@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+
+use crate::storage::{IdempotencyRecord, IdempotencyRepository, StorageError, StoredResponse};
+
+struct Entry {
+    request_hash: String,
+    response: Option<StoredResponse>,
+    claimed_at: DateTime<Utc>,
+}
+
+/// Process-local idempotency keys. Expired entries are dropped whenever a key
+/// is claimed.
+pub struct MemoryIdempotencyRepository {
+    entries: Mutex<HashMap<String, Entry>>,
+    ttl: Duration,
+}
+
+impl MemoryIdempotencyRepository {
+    pub fn new(ttl: Duration) -> Self {
+        MemoryIdempotencyRepository { entries: Mutex::new(HashMap::new()), ttl }
+    }
+}
+
+#[async_trait]
+impl IdempotencyRepository for MemoryIdempotencyRepository {
+    async fn claim(&self, key: &str, request_hash: &str) -> Result<Option<IdempotencyRecord>, StorageError> {
+        let now = Utc::now();
+        let cutoff = now - self.ttl;
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, entry| entry.claimed_at >= cutoff);
+
+        if let Some(entry) = entries.get(key) {
+            return Ok(Some(IdempotencyRecord {
+                request_hash: entry.request_hash.clone(),
+                response: entry.response.clone(),
+            }));
+        }
+        entries.insert(
+            key.to_string(),
+            Entry { request_hash: request_hash.to_string(), response: None, claimed_at: now },
+        );
+        Ok(None)
+    }
+
+    async fn complete(&self, key: &str, response: &StoredResponse) -> Result<(), StorageError> {
+        if let Some(entry) = self.entries.lock().unwrap().get_mut(key) {
+            entry.response = Some(response.clone());
+        }
+        Ok(())
+    }
+
+    async fn release(&self, key: &str) -> Result<(), StorageError> {
+        self.entries.lock().unwrap().remove(key);
+        Ok(())
+    }
+}
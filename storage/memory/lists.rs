@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use mongodb::bson::oid::ObjectId;
+
+use crate::models::{ListMember, ListRole, TaskList};
+use crate::storage::{no_owner_left, ListRepository, StorageError};
+use super::MemoryTaskRepository;
+
+/// Process-local shared lists, keyed by id, next to the task store holding
+/// their tasks.
+pub struct MemoryListRepository {
+    lists: RwLock<HashMap<ObjectId, TaskList>>,
+    tasks: Arc<MemoryTaskRepository>,
+}
+
+impl MemoryListRepository {
+    pub fn new(tasks: Arc<MemoryTaskRepository>) -> Self {
+        MemoryListRepository { lists: RwLock::default(), tasks }
+    }
+
+    /// Applies `change` to the list under the write lock and bumps
+    /// `updated_at`, unless it would leave the list without an owner.
+    fn modify<F>(&self, id: ObjectId, change: F) -> Result<Option<TaskList>, StorageError>
+    where
+        F: FnOnce(&mut TaskList),
+    {
+        let mut lists = self.lists.write().unwrap();
+        let Some(list) = lists.get_mut(&id) else {
+            return Ok(None);
+        };
+        let mut changed = list.clone();
+        change(&mut changed);
+        if changed.owner_count() == 0 {
+            return Err(no_owner_left());
+        }
+        changed.updated_at = Utc::now();
+        *list = changed.clone();
+        Ok(Some(changed))
+    }
+}
+
+#[async_trait]
+impl ListRepository for MemoryListRepository {
+    async fn create(&self, list: &TaskList) -> Result<(), StorageError> {
+        self.lists.write().unwrap().insert(list.id, list.clone());
+        Ok(())
+    }
+
+    async fn get(&self, id: ObjectId) -> Result<Option<TaskList>, StorageError> {
+        Ok(self.lists.read().unwrap().get(&id).cloned())
+    }
+
+    async fn for_member(&self, user_id: ObjectId) -> Result<Vec<TaskList>, StorageError> {
+        let lists = self.lists.read().unwrap();
+        let mut shared: Vec<TaskList> =
+            lists.values().filter(|list| list.role_of(&user_id).is_some()).cloned().collect();
+        shared.sort_by_key(|list| list.id);
+        Ok(shared)
+    }
+
+    async fn rename(&self, id: ObjectId, name: &str) -> Result<Option<TaskList>, StorageError> {
+        self.modify(id, |list| list.name = name.to_string())
+    }
+
+    async fn set_member(
+        &self,
+        id: ObjectId,
+        user_id: ObjectId,
+        role: ListRole,
+    ) -> Result<Option<TaskList>, StorageError> {
+        self.modify(id, |list| match list.members.iter_mut().find(|member| member.user_id == user_id) {
+            Some(member) => member.role = role,
+            None => list.members.push(ListMember { user_id, role }),
+        })
+    }
+
+    async fn remove_member(&self, id: ObjectId, user_id: ObjectId) -> Result<Option<TaskList>, StorageError> {
+        self.modify(id, |list| list.members.retain(|member| member.user_id != user_id))
+    }
+
+    async fn delete(&self, id: ObjectId) -> Result<bool, StorageError> {
+        let mut lists = self.lists.write().unwrap();
+        let list_id = id.to_hex();
+        self.tasks.tasks.write().unwrap().retain(|_, task| task.list_id.as_ref() != Some(&list_id));
+        Ok(lists.remove(&id).is_some())
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+
+use crate::storage::{RefreshToken, RefreshTokenRepository, Rotation, StorageError};
+
+struct Entry {
+    token: RefreshToken,
+    /// Set once the token has been exchanged for a new one.
+    rotated: bool,
+}
+
+/// Process-local refresh tokens, keyed by token hash. Expired tokens are
+/// dropped whenever a new one is stored.
+#[derive(Default)]
+pub struct MemoryRefreshTokenRepository {
+    tokens: Mutex<HashMap<String, Entry>>,
+}
+
+impl MemoryRefreshTokenRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+fn insert(tokens: &mut HashMap<String, Entry>, token: RefreshToken) {
+    let now = Utc::now();
+    tokens.retain(|_, entry| entry.token.expires_at > now);
+    tokens.insert(token.token_hash.clone(), Entry { token, rotated: false });
+}
+
+#[async_trait]
+impl RefreshTokenRepository for MemoryRefreshTokenRepository {
+    async fn create(&self, token: &RefreshToken) -> Result<(), StorageError> {
+        insert(&mut self.tokens.lock().unwrap(), token.clone());
+        Ok(())
+    }
+
+    async fn rotate(
+        &self,
+        token_hash: &str,
+        replacement_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Rotation, StorageError> {
+        let now = Utc::now();
+        let mut tokens = self.tokens.lock().unwrap();
+        let Some(entry) = tokens.get_mut(token_hash).filter(|entry| entry.token.expires_at > now) else {
+            return Ok(Rotation::Invalid);
+        };
+        if entry.rotated {
+            let session_id = entry.token.session_id;
+            tokens.retain(|_, entry| entry.token.session_id != session_id);
+            return Ok(Rotation::Reused);
+        }
+
+        entry.rotated = true;
+        let replacement = RefreshToken {
+            token_hash: replacement_hash.to_string(),
+            session_id: entry.token.session_id,
+            user_id: entry.token.user_id,
+            created_at: now,
+            expires_at,
+        };
+        insert(&mut tokens, replacement.clone());
+        Ok(Rotation::Rotated(replacement))
+    }
+
+    async fn revoke_session(&self, session_id: ObjectId) -> Result<(), StorageError> {
+        self.tokens.lock().unwrap().retain(|_, entry| entry.token.session_id != session_id);
+        Ok(())
+    }
+
+    async fn revoke_user(&self, user_id: ObjectId) -> Result<(), StorageError> {
+        self.tokens.lock().unwrap().retain(|_, entry| entry.token.user_id != user_id);
+        Ok(())
+    }
+}
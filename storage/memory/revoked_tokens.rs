@@ -0,0 +1,35 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use crate::storage::{RevokedTokenRepository, StorageError};
+
+/// Process-local access token denylist. Entries past their expiry are dropped
+/// whenever another token is revoked.
+#[derive(Default)]
+pub struct MemoryRevokedTokenRepository {
+    revoked: RwLock<HashMap<String, DateTime<Utc>>>,
+}
+
+impl MemoryRevokedTokenRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl RevokedTokenRepository for MemoryRevokedTokenRepository {
+    async fn revoke(&self, jti: &str, expires_at: DateTime<Utc>) -> Result<(), StorageError> {
+        let now = Utc::now();
+        let mut revoked = self.revoked.write().unwrap();
+        revoked.retain(|_, expires_at| *expires_at > now);
+        revoked.insert(jti.to_string(), expires_at);
+        Ok(())
+    }
+
+    async fn is_revoked(&self, jti: &str) -> Result<bool, StorageError> {
+        Ok(self.revoked.read().unwrap().contains_key(jti))
+    }
+}
@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+
+use crate::models::{Role, User};
+use crate::storage::{StorageError, UserPage, UserRepository};
+
+/// Process-local user accounts. Emails are checked for uniqueness under the
+/// same write lock that inserts the user.
+#[derive(Default)]
+pub struct MemoryUserRepository {
+    users: RwLock<HashMap<ObjectId, User>>,
+}
+
+impl MemoryUserRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn modify(&self, id: ObjectId, change: impl FnOnce(&mut User)) -> Option<User> {
+        let mut users = self.users.write().unwrap();
+        let user = users.get_mut(&id)?;
+        change(user);
+        Some(user.clone())
+    }
+}
+
+#[async_trait]
+impl UserRepository for MemoryUserRepository {
+    async fn create(&self, user: &User) -> Result<(), StorageError> {
+        let mut users = self.users.write().unwrap();
+        if users.values().any(|existing| existing.email == user.email) {
+            return Err(StorageError::Conflict(format!("email '{}' is already registered", user.email)));
+        }
+        users.insert(user.id, user.clone());
+        Ok(())
+    }
+
+    async fn get(&self, id: ObjectId) -> Result<Option<User>, StorageError> {
+        Ok(self.users.read().unwrap().get(&id).cloned())
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, StorageError> {
+        let users = self.users.read().unwrap();
+        Ok(users.values().find(|user| user.email == email).cloned())
+    }
+
+    async fn list(&self, offset: u64, limit: u64) -> Result<UserPage, StorageError> {
+        let users = self.users.read().unwrap();
+        let mut all: Vec<&User> = users.values().collect();
+        all.sort_by_key(|user| user.id);
+        let page = all.iter().skip(offset as usize).take(limit as usize).map(|user| (*user).clone()).collect();
+        Ok(UserPage { users: page, total: all.len() as u64 })
+    }
+
+    async fn set_role(&self, id: ObjectId, role: Role) -> Result<Option<User>, StorageError> {
+        Ok(self.modify(id, |user| user.role = role))
+    }
+
+    async fn set_disabled(
+        &self,
+        id: ObjectId,
+        disabled_at: Option<DateTime<Utc>>,
+    ) -> Result<Option<User>, StorageError> {
+        Ok(self.modify(id, |user| user.disabled_at = disabled_at))
+    }
+}
@@ -0,0 +1,737 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::stream::StreamExt;
+use mongodb::bson::{self, doc, Bson, Document};
+use mongodb::bson::oid::ObjectId;
+use mongodb::error::{ErrorKind, WriteFailure};
+use mongodb::options::{IndexOptions, ReturnDocument};
+use mongodb::IndexModel;
+
+use crate::models::{created_at_from_id, ChecklistItem, Progress, Task, TaskChanges, TaskInput};
+use crate::search::{SearchHit, DESCRIPTION_WEIGHT, TITLE_WEIGHT};
+use crate::subtasks::MAX_SUBTASK_DEPTH;
+use crate::validation::MAX_TAGS;
+use super::{
+    check_nesting, too_many_tags, SortField, SortKey, StorageError, TagCount, TaskFilter, TaskPage, TaskQuery,
+    TaskRepository, TaskScope,
+};
+
+mod api_keys;
+mod idempotency;
+mod lists;
+mod refresh_tokens;
+mod revoked_tokens;
+mod tenant;
+mod users;
+
+pub use api_keys::MongoApiKeyRepository;
+pub use idempotency::MongoIdempotencyRepository;
+pub use lists::MongoListRepository;
+pub use refresh_tokens::MongoRefreshTokenRepository;
+pub use revoked_tokens::MongoRevokedTokenRepository;
+pub use tenant::{tenant_database, TenantCollection};
+pub use users::MongoUserRepository;
+
+/// Server error code for a unique index violation.
+const DUPLICATE_KEY: i32 = 11000;
+
+fn is_duplicate_key(err: &mongodb::error::Error) -> bool {
+    match err.kind.as_ref() {
+        ErrorKind::Write(WriteFailure::WriteError(write_error)) => write_error.code == DUPLICATE_KEY,
+        ErrorKind::Command(command_error) => command_error.code == DUPLICATE_KEY,
+        ErrorKind::InsertMany(insert_error) => insert_error
+            .write_errors
+            .iter()
+            .flatten()
+            .any(|write_error| write_error.code == DUPLICATE_KEY),
+        _ => false,
+    }
+}
+
+impl From<mongodb::error::Error> for StorageError {
+    fn from(err: mongodb::error::Error) -> Self {
+        if is_duplicate_key(&err) {
+            StorageError::Conflict(err.to_string())
+        } else {
+            StorageError::Database(err.to_string())
+        }
+    }
+}
+
+pub struct MongoTaskRepository {
+    collection: TenantCollection,
+}
+
+impl MongoTaskRepository {
+    pub fn new(collection: TenantCollection) -> Self {
+        MongoTaskRepository { collection }
+    }
+
+    /// Called when a write matched nothing: fails with `PreconditionFailed`
+    /// if that was because the task exists at another revision.
+    async fn missing_or_stale(
+        &self,
+        scope: &TaskScope,
+        id: ObjectId,
+        expected_revisions: Option<&[i64]>,
+    ) -> Result<(), StorageError> {
+        let mut filter = scope_document(scope);
+        filter.insert("_id", id);
+        if expected_revisions.is_some() && self.collection.count_documents(filter).await? > 0 {
+            return Err(StorageError::PreconditionFailed);
+        }
+        Ok(())
+    }
+
+    /// Detaches the subtasks of the `deleted` tasks, recording the write.
+    async fn detach_subtasks(&self, deleted: &[ObjectId]) -> Result<(), StorageError> {
+        let update = vec![doc! { "$set": {
+            "parent_id": Bson::Null,
+            "updated_at": to_bson_datetime(Utc::now()),
+            "revision": { "$add": [{ "$ifNull": ["$revision", 0_i64] }, 1_i64] },
+        } }];
+        self.collection.update_many(doc! { "parent_id": { "$in": deleted } }, update).await?;
+        Ok(())
+    }
+
+    /// Checks that task `id` can be moved under `parent` with [`check_nesting`].
+    async fn check_nesting(&self, id: ObjectId, parent: ObjectId) -> Result<(), StorageError> {
+        // The parent and the tasks above it, then the levels of subtasks
+        // below the task, neither collected past the deepest nesting allowed.
+        let mut ancestors = Vec::new();
+        let mut next = Some(parent);
+        while let Some(ancestor) = next.filter(|id| ancestors.len() <= MAX_SUBTASK_DEPTH && !ancestors.contains(id)) {
+            ancestors.push(ancestor);
+            let doc = self.collection.find_one(doc! { "_id": ancestor }).projection(doc! { "parent_id": 1 }).await?;
+            next = doc.and_then(|doc| doc.get_object_id("parent_id").ok());
+        }
+
+        let mut level = vec![id];
+        let mut height = 0;
+        while height <= MAX_SUBTASK_DEPTH {
+            let mut cursor = self.collection
+                .find(doc! { "parent_id": { "$in": &level } })
+                .projection(doc! { "_id": 1 })
+                .await?;
+            level.clear();
+            while let Some(result) = cursor.next().await {
+                if let Ok(child) = result?.get_object_id("_id") {
+                    level.push(child);
+                }
+            }
+            if level.is_empty() {
+                break;
+            }
+            height += 1;
+        }
+        check_nesting(id, &ancestors, height)
+    }
+
+    /// Creates the indexes the queries rely on. Safe to run on every startup.
+    pub async fn ensure_indexes(&self) -> Result<(), StorageError> {
+        let text_index = IndexModel::builder()
+            .keys(doc! { "title": "text", "description": "text" })
+            .options(
+                IndexOptions::builder()
+                    .name("tasks_text".to_string())
+                    .weights(doc! { "title": TITLE_WEIGHT as i32, "description": DESCRIPTION_WEIGHT as i32 })
+                    .build(),
+            )
+            .build();
+        self.collection.create_index(text_index).await?;
+
+        let owner_index = IndexModel::builder()
+            .keys(doc! { "owner_id": 1, "_id": 1 })
+            .options(IndexOptions::builder().name("tasks_owner".to_string()).build())
+            .build();
+        self.collection.create_index(owner_index).await?;
+
+        // `$text` queries may only use `$or` when every branch is indexed,
+        // so list scopes need their own index next to `tasks_owner`.
+        let list_index = IndexModel::builder()
+            .keys(doc! { "list_id": 1, "_id": 1 })
+            .options(IndexOptions::builder().name("tasks_list".to_string()).build())
+            .build();
+        self.collection.create_index(list_index).await?;
+
+        // A multikey index, with one entry per tag, for tag filters and renames.
+        let tags_index = IndexModel::builder()
+            .keys(doc! { "tags": 1 })
+            .options(IndexOptions::builder().name("tasks_tags".to_string()).build())
+            .build();
+        self.collection.create_index(tags_index).await?;
+
+        let parent_index = IndexModel::builder()
+            .keys(doc! { "parent_id": 1 })
+            .options(IndexOptions::builder().name("tasks_parent".to_string()).build())
+            .build();
+        self.collection.create_index(parent_index).await?;
+        Ok(())
+    }
+}
+
+fn to_bson_datetime(value: DateTime<Utc>) -> bson::DateTime {
+    bson::DateTime::from_millis(value.timestamp_millis())
+}
+
+fn from_bson_datetime(value: bson::DateTime) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp_millis(value.timestamp_millis())
+}
+
+fn get_datetime(doc: &Document, key: &str) -> Option<DateTime<Utc>> {
+    doc.get_datetime(key).ok().copied().and_then(from_bson_datetime)
+}
+
+fn document_to_checklist_item(doc: &Document) -> Option<ChecklistItem> {
+    Some(ChecklistItem {
+        id: doc.get_str("id").ok()?.to_string(),
+        text: doc.get_str("text").unwrap_or_default().to_string(),
+        done: doc.get_bool("done").unwrap_or(false),
+        position: doc.get_i64("position").unwrap_or(0) as u32,
+    })
+}
+
+fn checklist_to_documents(checklist: &[ChecklistItem]) -> Vec<Document> {
+    checklist
+        .iter()
+        .map(|item| doc! { "id": &item.id, "text": &item.text, "done": item.done, "position": item.position as i64 })
+        .collect()
+}
+
+/// Builds a task from a stored document. Only `_id` is required: documents
+/// written by older versions lack the newer fields and get their defaults.
+fn document_to_task(doc: &Document) -> Option<Task> {
+    let id = doc.get_object_id("_id").ok()?;
+    let created_at = get_datetime(doc, "created_at").unwrap_or_else(|| created_at_from_id(&id));
+    Some(Task {
+        id: Some(id.to_hex()),
+        owner_id: doc.get_object_id("owner_id").ok().map(|owner| owner.to_hex()),
+        list_id: doc.get_object_id("list_id").ok().map(|list| list.to_hex()),
+        parent_id: doc.get_object_id("parent_id").ok().map(|parent| parent.to_hex()),
+        title: doc.get_str("title").unwrap_or_default().to_string(),
+        description: doc.get_str("description").ok().map(str::to_string),
+        completed: doc.get_bool("completed").unwrap_or(false),
+        completed_at: get_datetime(doc, "completed_at"),
+        due_at: get_datetime(doc, "due_at"),
+        priority: doc.get_str("priority").ok().and_then(|value| value.parse().ok()),
+        tags: doc
+            .get_array("tags")
+            .map(|tags| tags.iter().filter_map(|tag| tag.as_str().map(str::to_string)).collect())
+            .unwrap_or_default(),
+        checklist: doc
+            .get_array("checklist")
+            .map(|items| {
+                items.iter().filter_map(|item| item.as_document()).filter_map(document_to_checklist_item).collect()
+            })
+            .unwrap_or_default(),
+        created_at,
+        updated_at: get_datetime(doc, "updated_at").unwrap_or(created_at),
+        revision: doc.get_i64("revision").unwrap_or(0),
+        progress: None,
+    })
+}
+
+fn task_to_document(id: ObjectId, owner: ObjectId, list: Option<ObjectId>, task: &Task) -> Document {
+    doc! {
+        "_id": id,
+        "owner_id": owner,
+        "list_id": list,
+        "parent_id": task.parent_id.as_deref().and_then(|parent| ObjectId::parse_str(parent).ok()),
+        "title": &task.title,
+        "description": &task.description,
+        "completed": task.completed,
+        "completed_at": task.completed_at.map(to_bson_datetime),
+        "due_at": task.due_at.map(to_bson_datetime),
+        "priority": task.priority.map(|priority| priority.as_str()),
+        "tags": &task.tags,
+        "checklist": checklist_to_documents(&task.checklist),
+        "created_at": to_bson_datetime(task.created_at),
+        "updated_at": to_bson_datetime(task.updated_at),
+        "revision": task.revision,
+    }
+}
+
+/// Computed field holding a priority's position in ascending order; stored
+/// priorities are strings, which would otherwise sort alphabetically.
+const PRIORITY_RANK: &str = "_priority_rank";
+
+/// Matches the tasks in `scope`. A `null` list or owner id also matches
+/// documents from before lists or accounts existed, which lack the field.
+fn scope_document(scope: &TaskScope) -> Document {
+    let mut branches = Vec::new();
+    if let Some(owner) = scope.owner {
+        branches.push(doc! { "owner_id": owner, "list_id": Bson::Null });
+    }
+    if scope.unowned {
+        branches.push(doc! { "owner_id": Bson::Null, "list_id": Bson::Null });
+    }
+    if !scope.lists.is_empty() {
+        branches.push(doc! { "list_id": { "$in": scope.lists.clone() } });
+    }
+
+    match branches.len() {
+        // Keyed on `list_id` so that callers adding `_id` keep matching nothing.
+        0 => doc! { "list_id": { "$in": [] } },
+        1 => branches.remove(0),
+        _ => doc! { "$or": branches },
+    }
+}
+
+fn filter_document(scope: &TaskScope, filter: &TaskFilter) -> Document {
+    let mut document = scope_document(scope);
+
+    match filter.completed {
+        Some(true) => {
+            document.insert("completed", true);
+        }
+        // Documents from before completion tracking have no `completed` field.
+        Some(false) => {
+            document.insert("completed", doc! { "$ne": true });
+        }
+        None => {}
+    }
+    if !filter.priorities.is_empty() {
+        let priorities: Vec<&str> = filter.priorities.iter().map(|priority| priority.as_str()).collect();
+        document.insert("priority", doc! { "$in": priorities });
+    }
+
+    let mut due = Document::new();
+    if let Some(before) = filter.due_before {
+        due.insert("$lt", to_bson_datetime(before));
+    }
+    if let Some(after) = filter.due_after {
+        due.insert("$gt", to_bson_datetime(after));
+    }
+    if !due.is_empty() {
+        document.insert("due_at", due);
+    }
+    if !filter.tags.is_empty() {
+        document.insert("tags", doc! { "$all": filter.tags.clone() });
+    }
+    // A `null` parent also matches documents without a `parent_id` field.
+    if let Some(parent) = filter.parent {
+        document.insert("parent_id", parent);
+    }
+
+    document
+}
+
+fn sort_document(sort: &[SortKey]) -> Document {
+    let mut document = Document::new();
+    for key in sort {
+        let field = match key.field {
+            SortField::Title => "title",
+            SortField::Priority => PRIORITY_RANK,
+            SortField::DueAt => "due_at",
+            // ObjectIds start with the creation time and, unlike `created_at`,
+            // are present on every document.
+            SortField::CreatedAt => "_id",
+            SortField::UpdatedAt => "updated_at",
+            SortField::Completed => "completed",
+        };
+        // Re-inserting a key would overwrite its direction in place, so the
+        // first mention of a field wins, as it does in SQL.
+        if !document.contains_key(field) {
+            document.insert(field, if key.descending { -1 } else { 1 });
+        }
+    }
+    if !document.contains_key("_id") {
+        document.insert("_id", 1);
+    }
+    document
+}
+
+/// Selects the task by id within `scope` and, for conditional writes, by
+/// revision. Documents without a stored revision read as revision 0.
+fn task_filter(scope: &TaskScope, id: ObjectId, expected_revisions: Option<&[i64]>) -> Document {
+    let mut filter = scope_document(scope);
+    filter.insert("_id", id);
+    if let Some(revisions) = expected_revisions {
+        let mut accepted: Vec<Bson> = revisions.iter().map(|revision| Bson::Int64(*revision)).collect();
+        if revisions.contains(&0) {
+            accepted.push(Bson::Null);
+        }
+        filter.insert("revision", doc! { "$in": accepted });
+    }
+    filter
+}
+
+/// The `$set` stage of an update pipeline applying `changes` the way
+/// [`Task::apply`] does. A pipeline lets `$ifNull` keep the first completion
+/// time in the same atomic write; client values are wrapped in `$literal` so
+/// strings starting with `$` are not read as field paths.
+fn changes_document(changes: &TaskChanges, now: DateTime<Utc>) -> Document {
+    let now = to_bson_datetime(now);
+    let mut set = doc! {
+        "updated_at": now,
+        "revision": { "$add": [{ "$ifNull": ["$revision", 0_i64] }, 1_i64] },
+    };
+
+    if let Some(title) = &changes.title {
+        set.insert("title", doc! { "$literal": title });
+    }
+    if let Some(description) = &changes.description {
+        set.insert("description", doc! { "$literal": description });
+    }
+    if let Some(completed) = changes.completed {
+        set.insert("completed", completed);
+        if completed {
+            set.insert("completed_at", doc! { "$ifNull": ["$completed_at", now] });
+        } else {
+            set.insert("completed_at", Bson::Null);
+        }
+    }
+    if let Some(due_at) = changes.due_at {
+        set.insert("due_at", due_at.map(to_bson_datetime));
+    }
+    if let Some(priority) = changes.priority {
+        set.insert("priority", priority.map(|priority| priority.as_str()));
+    }
+    if let Some(tags) = &changes.tags {
+        set.insert("tags", doc! { "$literal": tags });
+    }
+    if let Some(parent_id) = changes.parent_id {
+        set.insert("parent_id", parent_id);
+    }
+    if let Some(checklist) = &changes.checklist {
+        set.insert("checklist", doc! { "$literal": checklist_to_documents(checklist) });
+    }
+
+    set
+}
+
+#[async_trait]
+impl TaskRepository for MongoTaskRepository {
+    async fn list(&self, scope: &TaskScope, query: &TaskQuery) -> Result<TaskPage, StorageError> {
+        let filter = filter_document(scope, &query.filter);
+        let total = self.collection.count_documents(filter.clone()).await?;
+
+        let mut matching = filter;
+        if let Some(after) = query.after {
+            matching.insert("_id", doc! { "$gt": after });
+        }
+        let pipeline = vec![
+            doc! { "$match": matching },
+            doc! { "$addFields": {
+                PRIORITY_RANK: { "$indexOfArray": [["low", "medium", "high", "urgent"], "$priority"] },
+            } },
+            doc! { "$sort": sort_document(&query.sort) },
+            doc! { "$skip": query.offset as i64 },
+            doc! { "$limit": query.limit as i64 + 1 },
+        ];
+        let mut cursor = self.collection.aggregate(pipeline).await?;
+        let mut tasks = Vec::new();
+
+        while let Some(result) = cursor.next().await {
+            if let Some(task) = document_to_task(&result?) {
+                tasks.push(task);
+            }
+        }
+
+        Ok(TaskPage::from_overfetch(tasks, query, total))
+    }
+
+    async fn search(&self, scope: &TaskScope, terms: &[String], limit: u64) -> Result<Vec<SearchHit>, StorageError> {
+        let mut filter = scope_document(scope);
+        filter.insert("$text", doc! { "$search": terms.join(" ") });
+        let mut cursor = self.collection
+            .find(filter)
+            .projection(doc! { "score": { "$meta": "textScore" } })
+            .sort(doc! { "score": { "$meta": "textScore" } })
+            .limit(limit as i64)
+            .await?;
+        let mut hits = Vec::new();
+
+        while let Some(result) = cursor.next().await {
+            let doc = result?;
+            if let Some(task) = document_to_task(&doc) {
+                let score = doc.get_f64("score").unwrap_or_default();
+                hits.push(SearchHit { task, score });
+            }
+        }
+
+        Ok(hits)
+    }
+
+    async fn get(&self, scope: &TaskScope, id: ObjectId) -> Result<Option<Task>, StorageError> {
+        let doc = self.collection.find_one(task_filter(scope, id, None)).await?;
+        Ok(doc.as_ref().and_then(document_to_task))
+    }
+
+    async fn create(&self, owner: ObjectId, list: Option<ObjectId>, input: TaskInput) -> Result<Task, StorageError> {
+        let id = ObjectId::new();
+        let task = input.into_task(id, owner, list, Utc::now());
+        self.collection.insert_one(task_to_document(id, owner, list, &task)).await?;
+        Ok(task)
+    }
+
+    async fn create_many(
+        &self,
+        owner: ObjectId,
+        list: Option<ObjectId>,
+        inputs: Vec<TaskInput>,
+    ) -> Vec<Result<Task, StorageError>> {
+        if inputs.is_empty() {
+            return Vec::new();
+        }
+        let now = Utc::now();
+        let mut tasks = Vec::with_capacity(inputs.len());
+        let mut documents = Vec::with_capacity(inputs.len());
+        for input in inputs {
+            let id = ObjectId::new();
+            let task = input.into_task(id, owner, list, now);
+            documents.push(task_to_document(id, owner, list, &task));
+            tasks.push(task);
+        }
+        let err = match self.collection.insert_many(documents).await {
+            Ok(_) => return tasks.into_iter().map(Ok).collect(),
+            Err(err) => err,
+        };
+
+        // The inserts are ordered: those before the first failing one were
+        // stored and none after it was tried.
+        let stored = match err.kind.as_ref() {
+            ErrorKind::InsertMany(insert_error) => {
+                insert_error.write_errors.iter().flatten().map(|write_error| write_error.index).min().unwrap_or(0)
+            }
+            _ => 0,
+        };
+        let err = StorageError::from(err);
+        tasks
+            .into_iter()
+            .enumerate()
+            .map(|(index, task)| if index < stored { Ok(task) } else { Err(err.clone()) })
+            .collect()
+    }
+
+    async fn update(
+        &self,
+        scope: &TaskScope,
+        id: ObjectId,
+        changes: TaskChanges,
+        expected_revisions: Option<Vec<i64>>,
+    ) -> Result<Option<Task>, StorageError> {
+        // Other tasks cannot be checked in the same write, so a new parent is
+        // checked both before and after it.
+        let moved = match changes.parent_id {
+            Some(Some(parent)) => match self.collection.find_one(task_filter(scope, id, None)).await? {
+                Some(existing) => {
+                    self.check_nesting(id, parent).await?;
+                    Some((parent, existing.get_object_id("parent_id").ok()))
+                }
+                None => None,
+            },
+            _ => None,
+        };
+
+        let filter = task_filter(scope, id, expected_revisions.as_deref());
+        let update = vec![doc! { "$set": changes_document(&changes, Utc::now()) }];
+
+        let doc = self.collection
+            .find_one_and_update(filter, update)
+            .return_document(ReturnDocument::After)
+            .await?;
+        let Some(doc) = doc else {
+            return self.missing_or_stale(scope, id, expected_revisions.as_deref()).await.map(|_| None);
+        };
+        if let Some((parent, previous)) = moved {
+            if let Err(err) = self.check_nesting(id, parent).await {
+                // Another move got in between; the previous parent goes back.
+                let update = vec![doc! { "$set": {
+                    "parent_id": previous,
+                    "updated_at": to_bson_datetime(Utc::now()),
+                    "revision": { "$add": [{ "$ifNull": ["$revision", 0_i64] }, 1_i64] },
+                } }];
+                self.collection.update_one(doc! { "_id": id, "parent_id": parent }, update).await?;
+                return Err(err);
+            }
+        }
+        Ok(document_to_task(&doc))
+    }
+
+    async fn delete(
+        &self,
+        scope: &TaskScope,
+        id: ObjectId,
+        expected_revisions: Option<Vec<i64>>,
+    ) -> Result<bool, StorageError> {
+        let filter = task_filter(scope, id, expected_revisions.as_deref());
+        let result = self.collection.delete_one(filter).await?;
+        if result.deleted_count > 0 {
+            self.detach_subtasks(&[id]).await?;
+            return Ok(true);
+        }
+        self.missing_or_stale(scope, id, expected_revisions.as_deref()).await.map(|_| false)
+    }
+
+    async fn delete_matching(&self, scope: &TaskScope, filter: &TaskFilter) -> Result<u64, StorageError> {
+        // The ids are collected first so that their subtasks can be found.
+        let mut cursor = self.collection
+            .find(filter_document(scope, filter))
+            .projection(doc! { "_id": 1 })
+            .await?;
+        let mut deleted = Vec::new();
+        while let Some(result) = cursor.next().await {
+            if let Ok(id) = result?.get_object_id("_id") {
+                deleted.push(id);
+            }
+        }
+        if deleted.is_empty() {
+            return Ok(0);
+        }
+
+        let result = self.collection.delete_many(doc! { "_id": { "$in": &deleted } }).await?;
+        self.detach_subtasks(&deleted).await?;
+        Ok(result.deleted_count)
+    }
+
+    async fn add_tags(&self, scope: &TaskScope, id: ObjectId, tags: &[String]) -> Result<Option<Task>, StorageError> {
+        // The limit is part of the filter so that concurrent adds cannot
+        // both pass it.
+        let mut filter = task_filter(scope, id, None);
+        filter.insert("$expr", doc! { "$lte": [
+            { "$size": { "$setUnion": [{ "$ifNull": ["$tags", []] }, { "$literal": tags }] } },
+            MAX_TAGS as i64,
+        ] });
+        let update = doc! {
+            "$addToSet": { "tags": { "$each": tags } },
+            "$set": { "updated_at": to_bson_datetime(Utc::now()) },
+            "$inc": { "revision": 1_i64 },
+        };
+
+        let doc = self.collection
+            .find_one_and_update(filter, update)
+            .return_document(ReturnDocument::After)
+            .await?;
+        match doc {
+            Some(doc) => Ok(document_to_task(&doc)),
+            None if self.collection.count_documents(task_filter(scope, id, None)).await? > 0 => Err(too_many_tags()),
+            None => Ok(None),
+        }
+    }
+
+    async fn remove_tags(
+        &self,
+        scope: &TaskScope,
+        id: ObjectId,
+        tags: &[String],
+    ) -> Result<Option<Task>, StorageError> {
+        let update = doc! {
+            "$pull": { "tags": { "$in": tags } },
+            "$set": { "updated_at": to_bson_datetime(Utc::now()) },
+            "$inc": { "revision": 1_i64 },
+        };
+        let doc = self.collection
+            .find_one_and_update(task_filter(scope, id, None), update)
+            .return_document(ReturnDocument::After)
+            .await?;
+        Ok(doc.as_ref().and_then(document_to_task))
+    }
+
+    async fn tag_counts(&self, scope: &TaskScope) -> Result<Vec<TagCount>, StorageError> {
+        let pipeline = vec![
+            doc! { "$match": scope_document(scope) },
+            doc! { "$unwind": "$tags" },
+            doc! { "$group": { "_id": "$tags", "count": { "$sum": 1_i64 } } },
+            doc! { "$sort": { "count": -1, "_id": 1 } },
+        ];
+        let mut cursor = self.collection.aggregate(pipeline).await?;
+        let mut counts = Vec::new();
+
+        while let Some(result) = cursor.next().await {
+            let doc = result?;
+            if let Ok(name) = doc.get_str("_id") {
+                counts.push(TagCount { name: name.to_string(), count: doc.get_i64("count").unwrap_or(0) as u64 });
+            }
+        }
+
+        Ok(counts)
+    }
+
+    async fn rename_tags(&self, scope: &TaskScope, from: &[String], to: &str) -> Result<u64, StorageError> {
+        let mut filter = scope_document(scope);
+        filter.insert("tags", doc! { "$in": from });
+        // Rebuilds the array in order, replacing the renamed tags and keeping
+        // only the first occurrence of each tag.
+        let renamed = doc! { "$cond": [{ "$in": ["$$this", { "$literal": from }] }, { "$literal": to }, "$$this"] };
+        let tags = doc! { "$reduce": {
+            "input": "$tags",
+            "initialValue": [],
+            "in": { "$let": {
+                "vars": { "tag": renamed },
+                "in": { "$cond": [
+                    { "$in": ["$$tag", "$$value"] },
+                    "$$value",
+                    { "$concatArrays": ["$$value", ["$$tag"]] },
+                ] },
+            } },
+        } };
+        let update = vec![doc! { "$set": {
+            "tags": tags,
+            "updated_at": to_bson_datetime(Utc::now()),
+            "revision": { "$add": [{ "$ifNull": ["$revision", 0_i64] }, 1_i64] },
+        } }];
+
+        let result = self.collection.update_many(filter, update).await?;
+        Ok(result.modified_count)
+    }
+
+    async fn subtask_progress(&self, parents: &[ObjectId]) -> Result<HashMap<String, Progress>, StorageError> {
+        let pipeline = vec![
+            doc! { "$match": { "parent_id": { "$in": parents } } },
+            doc! { "$group": {
+                "_id": "$parent_id",
+                "done": { "$sum": { "$cond": [{ "$eq": ["$completed", true] }, 1_i64, 0_i64] } },
+                "total": { "$sum": 1_i64 },
+            } },
+        ];
+        let mut cursor = self.collection.aggregate(pipeline).await?;
+        let mut progress = HashMap::new();
+
+        while let Some(result) = cursor.next().await {
+            let doc = result?;
+            if let Ok(parent) = doc.get_object_id("_id") {
+                let done = doc.get_i64("done").unwrap_or(0) as u64;
+                let total = doc.get_i64("total").unwrap_or(0) as u64;
+                progress.insert(parent.to_hex(), Progress { done, total });
+            }
+        }
+
+        Ok(progress)
+    }
+
+    async fn transfer(&self, id: ObjectId, new_owner: ObjectId) -> Result<Option<Task>, StorageError> {
+        // The subtasks are collected level by level so that a single update
+        // moves them together with the task.
+        let mut moved = vec![id];
+        let mut parents = vec![id];
+        while !parents.is_empty() {
+            let mut cursor = self.collection
+                .find(doc! { "parent_id": { "$in": &parents }, "_id": { "$nin": &moved } })
+                .projection(doc! { "_id": 1 })
+                .await?;
+            parents.clear();
+            while let Some(result) = cursor.next().await {
+                if let Ok(child) = result?.get_object_id("_id") {
+                    parents.push(child);
+                }
+            }
+            moved.extend(&parents);
+        }
+
+        let update = vec![doc! { "$set": {
+            "owner_id": new_owner,
+            "updated_at": to_bson_datetime(Utc::now()),
+            "revision": { "$add": [{ "$ifNull": ["$revision", 0_i64] }, 1_i64] },
+            "parent_id": { "$cond": [{ "$eq": ["$_id", id] }, Bson::Null, "$parent_id"] },
+        } }];
+        let result = self.collection.update_many(doc! { "_id": { "$in": &moved } }, update).await?;
+        if result.matched_count == 0 {
+            return Ok(None);
+        }
+        let doc = self.collection.find_one(doc! { "_id": id }).await?;
+        Ok(doc.as_ref().and_then(document_to_task))
+    }
+}
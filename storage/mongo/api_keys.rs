@@ -0,0 +1,105 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::stream::StreamExt;
+use mongodb::bson::oid::ObjectId;
+use mongodb::bson::{doc, Document};
+use mongodb::options::IndexOptions;
+use mongodb::IndexModel;
+
+use crate::models::{created_at_from_id, ApiKey, Scope};
+use crate::storage::{ApiKeyRepository, StorageError};
+use super::{get_datetime, to_bson_datetime, TenantCollection};
+
+/// API keys in the `api_keys` collection, looked up through a unique index
+/// on `key_hash`.
+pub struct MongoApiKeyRepository {
+    collection: TenantCollection,
+}
+
+impl MongoApiKeyRepository {
+    pub fn new(collection: TenantCollection) -> Self {
+        MongoApiKeyRepository { collection }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), StorageError> {
+        let hash_index = IndexModel::builder()
+            .keys(doc! { "key_hash": 1 })
+            .options(IndexOptions::builder().name("api_keys_hash".to_string()).unique(true).build())
+            .build();
+        let user_index = IndexModel::builder()
+            .keys(doc! { "user_id": 1, "_id": 1 })
+            .options(IndexOptions::builder().name("api_keys_user".to_string()).build())
+            .build();
+        self.collection.create_indexes([hash_index, user_index]).await?;
+        Ok(())
+    }
+}
+
+fn document_to_api_key(doc: &Document) -> Option<ApiKey> {
+    let id = doc.get_object_id("_id").ok()?;
+    let scopes = doc
+        .get_array("scopes")
+        .ok()?
+        .iter()
+        .filter_map(|scope| scope.as_str()?.parse::<Scope>().ok())
+        .collect();
+    Some(ApiKey {
+        id,
+        user_id: doc.get_object_id("user_id").ok()?,
+        name: doc.get_str("name").ok()?.to_string(),
+        prefix: doc.get_str("prefix").ok()?.to_string(),
+        key_hash: doc.get_str("key_hash").ok()?.to_string(),
+        scopes,
+        created_at: get_datetime(doc, "created_at").unwrap_or_else(|| created_at_from_id(&id)),
+        last_used_at: get_datetime(doc, "last_used_at"),
+    })
+}
+
+#[async_trait]
+impl ApiKeyRepository for MongoApiKeyRepository {
+    async fn create(&self, key: &ApiKey) -> Result<(), StorageError> {
+        let scopes: Vec<&str> = key.scopes.iter().map(Scope::as_str).collect();
+        let doc = doc! {
+            "_id": key.id,
+            "user_id": key.user_id,
+            "name": &key.name,
+            "prefix": &key.prefix,
+            "key_hash": &key.key_hash,
+            "scopes": scopes,
+            "created_at": to_bson_datetime(key.created_at),
+            "last_used_at": key.last_used_at.map(to_bson_datetime),
+        };
+        self.collection.insert_one(doc).await?;
+        Ok(())
+    }
+
+    async fn list(&self, user_id: ObjectId) -> Result<Vec<ApiKey>, StorageError> {
+        let mut cursor = self.collection.find(doc! { "user_id": user_id }).sort(doc! { "_id": 1 }).await?;
+        let mut keys = Vec::new();
+
+        while let Some(result) = cursor.next().await {
+            if let Some(key) = document_to_api_key(&result?) {
+                keys.push(key);
+            }
+        }
+
+        Ok(keys)
+    }
+
+    async fn find_by_hash(&self, key_hash: &str) -> Result<Option<ApiKey>, StorageError> {
+        let doc = self.collection.find_one(doc! { "key_hash": key_hash }).await?;
+        Ok(doc.as_ref().and_then(document_to_api_key))
+    }
+
+    async fn touch(&self, id: ObjectId, at: DateTime<Utc>) -> Result<(), StorageError> {
+        self.collection
+            .update_one(doc! { "_id": id }, doc! { "$set": { "last_used_at": to_bson_datetime(at) } })
+            .await?;
+        Ok(())
+    }
+
+    async fn revoke(&self, user_id: ObjectId, id: ObjectId) -> Result<bool, StorageError> {
+        let result = self.collection.delete_one(doc! { "_id": id, "user_id": user_id }).await?;
+        Ok(result.deleted_count > 0)
+    }
+}
@@ -0,0 +1,88 @@
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use mongodb::bson::{self, doc, Document};
+use mongodb::options::IndexOptions;
+use mongodb::IndexModel;
+
+use crate::storage::{IdempotencyRecord, IdempotencyRepository, StorageError, StoredResponse};
+use super::{is_duplicate_key, to_bson_datetime, TenantCollection};
+
+/// Idempotency keys in their own collection, keyed by `_id`. A TTL index on
+/// `created_at` lets the server drop expired keys; since it only sweeps about
+/// once a minute, `claim` also ignores keys that have expired but linger.
+pub struct MongoIdempotencyRepository {
+    collection: TenantCollection,
+    ttl: Duration,
+}
+
+impl MongoIdempotencyRepository {
+    pub fn new(collection: TenantCollection, ttl: Duration) -> Self {
+        MongoIdempotencyRepository { collection, ttl }
+    }
+
+    /// Creates the TTL index. An existing index keeps the TTL it was created
+    /// with; drop it to apply a changed `IDEMPOTENCY_TTL_SECONDS`.
+    pub async fn ensure_indexes(&self) -> Result<(), StorageError> {
+        let ttl = self.ttl.to_std().map_err(|err| StorageError::Database(err.to_string()))?;
+        let ttl_index = IndexModel::builder()
+            .keys(doc! { "created_at": 1 })
+            .options(
+                IndexOptions::builder()
+                    .name("idempotency_keys_ttl".to_string())
+                    .expire_after(ttl)
+                    .build(),
+            )
+            .build();
+        self.collection.create_index(ttl_index).await?;
+        Ok(())
+    }
+}
+
+fn document_to_record(doc: &Document) -> Result<IdempotencyRecord, StorageError> {
+    let response = match doc.get_document("response") {
+        Ok(response) => Some(
+            bson::from_document::<StoredResponse>(response.clone())
+                .map_err(|err| StorageError::Database(err.to_string()))?,
+        ),
+        Err(_) => None,
+    };
+    Ok(IdempotencyRecord {
+        request_hash: doc.get_str("request_hash").unwrap_or_default().to_string(),
+        response,
+    })
+}
+
+#[async_trait]
+impl IdempotencyRepository for MongoIdempotencyRepository {
+    async fn claim(&self, key: &str, request_hash: &str) -> Result<Option<IdempotencyRecord>, StorageError> {
+        let now = Utc::now();
+        let cutoff = to_bson_datetime(now - self.ttl);
+        self.collection
+            .delete_one(doc! { "_id": key, "created_at": { "$lt": cutoff } })
+            .await?;
+
+        let claim = doc! { "_id": key, "request_hash": request_hash, "created_at": to_bson_datetime(now) };
+        match self.collection.insert_one(claim).await {
+            Ok(_) => Ok(None),
+            Err(err) if is_duplicate_key(&err) => match self.collection.find_one(doc! { "_id": key }).await? {
+                Some(doc) => document_to_record(&doc).map(Some),
+                // Released between our insert and this read.
+                None => Err(StorageError::Conflict("idempotency key is being released".to_string())),
+            },
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn complete(&self, key: &str, response: &StoredResponse) -> Result<(), StorageError> {
+        let response = bson::to_document(response).map_err(|err| StorageError::Database(err.to_string()))?;
+        self.collection
+            .update_one(doc! { "_id": key }, doc! { "$set": { "response": response } })
+            .await?;
+        Ok(())
+    }
+
+    async fn release(&self, key: &str) -> Result<(), StorageError> {
+        self.collection.delete_one(doc! { "_id": key }).await?;
+        Ok(())
+    }
+}
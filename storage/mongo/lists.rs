@@ -0,0 +1,194 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use futures::stream::StreamExt;
+use mongodb::bson::oid::ObjectId;
+use mongodb::bson::{doc, Document};
+use mongodb::options::{IndexOptions, ReturnDocument};
+use mongodb::IndexModel;
+
+use crate::models::{created_at_from_id, ListMember, ListRole, TaskList};
+use crate::storage::{no_owner_left, ListRepository, StorageError};
+use super::{get_datetime, to_bson_datetime, TenantCollection};
+
+/// Shared lists in the `lists` collection, with their members embedded and
+/// indexed by `members.user_id`. Their tasks live in `tasks`.
+pub struct MongoListRepository {
+    collection: TenantCollection,
+    tasks: TenantCollection,
+}
+
+impl MongoListRepository {
+    pub fn new(collection: TenantCollection, tasks: TenantCollection) -> Self {
+        MongoListRepository { collection, tasks }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), StorageError> {
+        let member_index = IndexModel::builder()
+            .keys(doc! { "members.user_id": 1, "_id": 1 })
+            .options(IndexOptions::builder().name("lists_member".to_string()).build())
+            .build();
+        self.collection.create_index(member_index).await?;
+        Ok(())
+    }
+
+    /// Applies `update` to the list matching `filter`, bumping `updated_at`.
+    async fn update(&self, filter: Document, update: Document) -> Result<Option<TaskList>, StorageError> {
+        self.update_members(filter, update, None).await
+    }
+
+    /// Like [`update`](Self::update), with `array_filters` naming the members
+    /// that `$[...]` placeholders in the update apply to.
+    async fn update_members(
+        &self,
+        filter: Document,
+        mut update: Document,
+        array_filters: Option<Vec<Document>>,
+    ) -> Result<Option<TaskList>, StorageError> {
+        update.insert("$set", {
+            let mut set = update.get_document("$set").cloned().unwrap_or_default();
+            set.insert("updated_at", to_bson_datetime(Utc::now()));
+            set
+        });
+        let mut action = self.collection.find_one_and_update(filter, update).return_document(ReturnDocument::After);
+        if let Some(array_filters) = array_filters {
+            action = action.array_filters(array_filters);
+        }
+        let doc = action.await?;
+        Ok(doc.as_ref().and_then(document_to_list))
+    }
+}
+
+fn document_to_list(doc: &Document) -> Option<TaskList> {
+    let id = doc.get_object_id("_id").ok()?;
+    let members = doc
+        .get_array("members")
+        .ok()?
+        .iter()
+        .filter_map(|member| {
+            let member = member.as_document()?;
+            Some(ListMember {
+                user_id: member.get_object_id("user_id").ok()?,
+                role: member.get_str("role").ok()?.parse().ok()?,
+            })
+        })
+        .collect();
+    let created_at = get_datetime(doc, "created_at").unwrap_or_else(|| created_at_from_id(&id));
+    Some(TaskList {
+        id,
+        name: doc.get_str("name").ok()?.to_string(),
+        members,
+        created_at,
+        updated_at: get_datetime(doc, "updated_at").unwrap_or(created_at),
+    })
+}
+
+fn member_document(user_id: ObjectId, role: ListRole) -> Document {
+    doc! { "user_id": user_id, "role": role.as_str() }
+}
+
+/// Matches lists with an owner other than `user_id`.
+fn other_owner(user_id: ObjectId) -> Document {
+    doc! { "$elemMatch": { "user_id": { "$ne": user_id }, "role": ListRole::Owner.as_str() } }
+}
+
+#[async_trait]
+impl ListRepository for MongoListRepository {
+    async fn create(&self, list: &TaskList) -> Result<(), StorageError> {
+        let members: Vec<Document> =
+            list.members.iter().map(|member| member_document(member.user_id, member.role)).collect();
+        let doc = doc! {
+            "_id": list.id,
+            "name": &list.name,
+            "members": members,
+            "created_at": to_bson_datetime(list.created_at),
+            "updated_at": to_bson_datetime(list.updated_at),
+        };
+        self.collection.insert_one(doc).await?;
+        Ok(())
+    }
+
+    async fn get(&self, id: ObjectId) -> Result<Option<TaskList>, StorageError> {
+        let doc = self.collection.find_one(doc! { "_id": id }).await?;
+        Ok(doc.as_ref().and_then(document_to_list))
+    }
+
+    async fn for_member(&self, user_id: ObjectId) -> Result<Vec<TaskList>, StorageError> {
+        let mut cursor = self.collection.find(doc! { "members.user_id": user_id }).sort(doc! { "_id": 1 }).await?;
+        let mut lists = Vec::new();
+
+        while let Some(result) = cursor.next().await {
+            if let Some(list) = document_to_list(&result?) {
+                lists.push(list);
+            }
+        }
+
+        Ok(lists)
+    }
+
+    async fn rename(&self, id: ObjectId, name: &str) -> Result<Option<TaskList>, StorageError> {
+        self.update(doc! { "_id": id }, doc! { "$set": { "name": name } }).await
+    }
+
+    async fn set_member(
+        &self,
+        id: ObjectId,
+        user_id: ObjectId,
+        role: ListRole,
+    ) -> Result<Option<TaskList>, StorageError> {
+        // Change the role of an existing member, or else add a new one. A
+        // demotion only matches while someone else owns the list too.
+        let mut filter = doc! { "_id": id, "members.user_id": user_id };
+        if role != ListRole::Owner {
+            filter.insert("members", other_owner(user_id));
+        }
+        let changed = self
+            .update_members(
+                filter,
+                doc! { "$set": { "members.$[member].role": role.as_str() } },
+                Some(vec![doc! { "member.user_id": user_id }]),
+            )
+            .await?;
+        if changed.is_some() {
+            return Ok(changed);
+        }
+        let added = self
+            .update(
+                doc! { "_id": id, "members.user_id": { "$ne": user_id } },
+                doc! { "$push": { "members": member_document(user_id, role) } },
+            )
+            .await?;
+        match added {
+            Some(list) => Ok(Some(list)),
+            None => match self.get(id).await? {
+                Some(list) if list.role_of(&user_id) == Some(ListRole::Owner) && list.owner_count() == 1 => {
+                    Err(no_owner_left())
+                }
+                // The member was added concurrently between the two updates.
+                list => Ok(list),
+            },
+        }
+    }
+
+    async fn remove_member(&self, id: ObjectId, user_id: ObjectId) -> Result<Option<TaskList>, StorageError> {
+        // Only matches while the member is not an owner or is not the last one.
+        let owner = ListRole::Owner.as_str();
+        let filter = doc! {
+            "_id": id,
+            "$or": [
+                { "members": { "$not": { "$elemMatch": { "user_id": user_id, "role": owner } } } },
+                { "members": other_owner(user_id) },
+            ],
+        };
+        match self.update(filter, doc! { "$pull": { "members": { "user_id": user_id } } }).await? {
+            Some(list) => Ok(Some(list)),
+            None if self.get(id).await?.is_some() => Err(no_owner_left()),
+            None => Ok(None),
+        }
+    }
+
+    async fn delete(&self, id: ObjectId) -> Result<bool, StorageError> {
+        let result = self.collection.delete_one(doc! { "_id": id }).await?;
+        self.tasks.delete_many(doc! { "list_id": id }).await?;
+        Ok(result.deleted_count > 0)
+    }
+}
@@ -0,0 +1,114 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use mongodb::bson::{doc, Bson, Document};
+use mongodb::options::IndexOptions;
+use mongodb::IndexModel;
+
+use crate::storage::{RefreshToken, RefreshTokenRepository, Rotation, StorageError};
+use super::{to_bson_datetime, TenantCollection};
+
+/// Refresh tokens in the `refresh_tokens` collection, keyed by token hash.
+/// A TTL index removes them once `expires_at` has passed.
+pub struct MongoRefreshTokenRepository {
+    collection: TenantCollection,
+}
+
+impl MongoRefreshTokenRepository {
+    pub fn new(collection: TenantCollection) -> Self {
+        MongoRefreshTokenRepository { collection }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), StorageError> {
+        let ttl_index = IndexModel::builder()
+            .keys(doc! { "expires_at": 1 })
+            .options(
+                IndexOptions::builder()
+                    .name("refresh_tokens_ttl".to_string())
+                    .expire_after(Duration::ZERO)
+                    .build(),
+            )
+            .build();
+        let session_index = IndexModel::builder()
+            .keys(doc! { "session_id": 1 })
+            .options(IndexOptions::builder().name("refresh_tokens_session".to_string()).build())
+            .build();
+        self.collection.create_indexes([ttl_index, session_index]).await?;
+        Ok(())
+    }
+}
+
+fn token_to_document(token: &RefreshToken) -> Document {
+    doc! {
+        "_id": &token.token_hash,
+        "session_id": token.session_id,
+        "user_id": token.user_id,
+        "created_at": to_bson_datetime(token.created_at),
+        "expires_at": to_bson_datetime(token.expires_at),
+        "rotated_at": Bson::Null,
+    }
+}
+
+#[async_trait]
+impl RefreshTokenRepository for MongoRefreshTokenRepository {
+    async fn create(&self, token: &RefreshToken) -> Result<(), StorageError> {
+        self.collection.insert_one(token_to_document(token)).await?;
+        Ok(())
+    }
+
+    async fn rotate(
+        &self,
+        token_hash: &str,
+        replacement_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Rotation, StorageError> {
+        let now = to_bson_datetime(Utc::now());
+        // The TTL monitor only runs about once a minute, so expiry is checked
+        // here too. Marking the token as rotated in the same write that checks
+        // it makes concurrent refreshes with one token see it as reused.
+        let current = doc! { "_id": token_hash, "expires_at": { "$gt": now } };
+        let mut unrotated = current.clone();
+        unrotated.insert("rotated_at", Bson::Null);
+
+        let rotated = self.collection
+            .find_one_and_update(unrotated, doc! { "$set": { "rotated_at": now } })
+            .await?;
+        let Some(rotated) = rotated else {
+            return match self.collection.find_one(current).await? {
+                Some(reused) => {
+                    if let Ok(session_id) = reused.get_object_id("session_id") {
+                        self.revoke_session(session_id).await?;
+                    }
+                    Ok(Rotation::Reused)
+                }
+                None => Ok(Rotation::Invalid),
+            };
+        };
+
+        let (Ok(session_id), Ok(user_id)) = (rotated.get_object_id("session_id"), rotated.get_object_id("user_id"))
+        else {
+            return Ok(Rotation::Invalid);
+        };
+        let replacement = RefreshToken {
+            token_hash: replacement_hash.to_string(),
+            session_id,
+            user_id,
+            created_at: Utc::now(),
+            expires_at,
+        };
+        self.create(&replacement).await?;
+        Ok(Rotation::Rotated(replacement))
+    }
+
+    async fn revoke_session(&self, session_id: ObjectId) -> Result<(), StorageError> {
+        self.collection.delete_many(doc! { "session_id": session_id }).await?;
+        Ok(())
+    }
+
+    async fn revoke_user(&self, user_id: ObjectId) -> Result<(), StorageError> {
+        self.collection.delete_many(doc! { "user_id": user_id }).await?;
+        Ok(())
+    }
+}
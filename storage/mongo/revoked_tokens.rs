@@ -0,0 +1,54 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mongodb::bson::doc;
+use mongodb::options::IndexOptions;
+use mongodb::IndexModel;
+
+use crate::storage::{RevokedTokenRepository, StorageError};
+use super::{to_bson_datetime, TenantCollection};
+
+/// Access token denylist in the `revoked_tokens` collection, keyed by `jti`.
+/// A TTL index removes entries once the token would have expired anyway.
+pub struct MongoRevokedTokenRepository {
+    collection: TenantCollection,
+}
+
+impl MongoRevokedTokenRepository {
+    pub fn new(collection: TenantCollection) -> Self {
+        MongoRevokedTokenRepository { collection }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), StorageError> {
+        let ttl_index = IndexModel::builder()
+            .keys(doc! { "expires_at": 1 })
+            .options(
+                IndexOptions::builder()
+                    .name("revoked_tokens_ttl".to_string())
+                    .expire_after(Duration::ZERO)
+                    .build(),
+            )
+            .build();
+        self.collection.create_index(ttl_index).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RevokedTokenRepository for MongoRevokedTokenRepository {
+    async fn revoke(&self, jti: &str, expires_at: DateTime<Utc>) -> Result<(), StorageError> {
+        self.collection
+            .update_one(
+                doc! { "_id": jti },
+                doc! { "$set": { "expires_at": to_bson_datetime(expires_at) } },
+            )
+            .upsert(true)
+            .await?;
+        Ok(())
+    }
+
+    async fn is_revoked(&self, jti: &str) -> Result<bool, StorageError> {
+        Ok(self.collection.count_documents(doc! { "_id": jti }).limit(1).await? > 0)
+    }
+}
@@ -0,0 +1,157 @@
+use mongodb::action::{
+    Aggregate, CountDocuments, CreateIndex, Delete, Find, FindOne, FindOneAndUpdate, InsertMany, InsertOne, Multiple,
+    Update,
+};
+use mongodb::bson::{doc, Document};
+use mongodb::options::UpdateModifications;
+use mongodb::{Collection, IndexModel};
+
+/// Field tagging each document with its tenant under the shared strategy.
+const TENANT_FIELD: &str = "tenant_id";
+
+/// The database of `tenant` when each tenant gets one of its own, named after
+/// the shared `database`.
+pub fn tenant_database(database: &str, tenant: &str) -> String {
+    format!("{}_{}", database, tenant)
+}
+
+/// A collection as seen by one tenant. When tenants share the collection,
+/// filters and pipelines are narrowed to the tenant's documents, inserted
+/// documents are tagged with it, and index keys start with it so that unique
+/// indexes hold per tenant. TTL indexes must stay single-field and are left
+/// alone. Without a tenant every call passes straight through.
+///
+/// Moving a collection between the two modes changes its index keys, so the
+/// old indexes have to be dropped first.
+pub struct TenantCollection {
+    inner: Collection<Document>,
+    tenant: Option<String>,
+}
+
+impl TenantCollection {
+    pub fn new(inner: Collection<Document>, tenant: Option<String>) -> Self {
+        TenantCollection { inner, tenant }
+    }
+
+    fn scoped(&self, mut document: Document) -> Document {
+        if let Some(tenant) = &self.tenant {
+            document.insert(TENANT_FIELD, tenant);
+        }
+        document
+    }
+
+    fn scoped_index(&self, mut index: IndexModel) -> IndexModel {
+        let expires = index.options.as_ref().is_some_and(|options| options.expire_after.is_some());
+        if self.tenant.is_some() && !expires {
+            let mut keys = doc! { TENANT_FIELD: 1 };
+            keys.extend(index.keys);
+            index.keys = keys;
+        }
+        index
+    }
+
+    pub fn find(&self, filter: Document) -> Find<'_, Document> {
+        self.inner.find(self.scoped(filter))
+    }
+
+    pub fn find_one(&self, filter: Document) -> FindOne<'_, Document> {
+        self.inner.find_one(self.scoped(filter))
+    }
+
+    pub fn count_documents(&self, filter: Document) -> CountDocuments<'_> {
+        self.inner.count_documents(self.scoped(filter))
+    }
+
+    pub fn aggregate(&self, pipeline: Vec<Document>) -> Aggregate<'_> {
+        let stages = self.tenant.iter().map(|tenant| doc! { "$match": { TENANT_FIELD: tenant } });
+        self.inner.aggregate(stages.chain(pipeline))
+    }
+
+    pub fn insert_one(&self, document: Document) -> InsertOne<'_> {
+        self.inner.insert_one(self.scoped(document))
+    }
+
+    pub fn insert_many(&self, documents: Vec<Document>) -> InsertMany<'_> {
+        self.inner.insert_many(documents.into_iter().map(|document| self.scoped(document)))
+    }
+
+    /// Upserts insert the filter's equality fields, `tenant_id` included.
+    pub fn update_one(&self, filter: Document, update: impl Into<UpdateModifications>) -> Update<'_> {
+        self.inner.update_one(self.scoped(filter), update)
+    }
+
+    pub fn update_many(&self, filter: Document, update: impl Into<UpdateModifications>) -> Update<'_> {
+        self.inner.update_many(self.scoped(filter), update)
+    }
+
+    pub fn find_one_and_update(
+        &self,
+        filter: Document,
+        update: impl Into<UpdateModifications>,
+    ) -> FindOneAndUpdate<'_, Document> {
+        self.inner.find_one_and_update(self.scoped(filter), update)
+    }
+
+    pub fn delete_one(&self, filter: Document) -> Delete<'_> {
+        self.inner.delete_one(self.scoped(filter))
+    }
+
+    pub fn delete_many(&self, filter: Document) -> Delete<'_> {
+        self.inner.delete_many(self.scoped(filter))
+    }
+
+    pub fn create_index(&self, index: IndexModel) -> CreateIndex<'_> {
+        self.inner.create_index(self.scoped_index(index))
+    }
+
+    pub fn create_indexes(&self, indexes: impl IntoIterator<Item = IndexModel>) -> CreateIndex<'_, Multiple> {
+        self.inner.create_indexes(indexes.into_iter().map(|index| self.scoped_index(index)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use mongodb::options::{ClientOptions, IndexOptions, ServerAddress};
+    use mongodb::Client;
+
+    use super::*;
+
+    /// The `tasks` collection as seen by `tenant`. The client connects lazily,
+    /// so no server is needed as long as nothing is sent.
+    fn tasks(tenant: Option<&str>) -> TenantCollection {
+        let address = ServerAddress::Tcp { host: "localhost".to_string(), port: None };
+        let client = Client::with_options(ClientOptions::builder().hosts(vec![address]).build()).unwrap();
+        TenantCollection::new(client.database("rust_backend").collection("tasks"), tenant.map(str::to_string))
+    }
+
+    fn index(keys: Document, expire_after: Option<Duration>) -> IndexModel {
+        let options = IndexOptions::builder().name("tasks_index".to_string()).expire_after(expire_after).build();
+        IndexModel::builder().keys(keys).options(options).build()
+    }
+
+    #[test]
+    fn names_tenant_databases_after_the_shared_one() {
+        assert_eq!(tenant_database("rust_backend", "acme"), "rust_backend_acme");
+    }
+
+    #[actix_web::test]
+    async fn tags_documents_and_index_keys_with_the_tenant() {
+        let tasks = tasks(Some("acme"));
+        assert_eq!(tasks.scoped(doc! { "title": "milk" }), doc! { "title": "milk", "tenant_id": "acme" });
+        let scoped = tasks.scoped_index(index(doc! { "owner_id": 1, "_id": 1 }, None));
+        assert_eq!(scoped.keys, doc! { "tenant_id": 1, "owner_id": 1, "_id": 1 });
+        assert_eq!(scoped.options.and_then(|options| options.name).as_deref(), Some("tasks_index"));
+    }
+
+    #[actix_web::test]
+    async fn leaves_ttl_indexes_and_single_tenant_collections_alone() {
+        let ttl = index(doc! { "expires_at": 1 }, Some(Duration::ZERO));
+        assert_eq!(tasks(Some("acme")).scoped_index(ttl).keys, doc! { "expires_at": 1 });
+
+        let tasks = tasks(None);
+        assert_eq!(tasks.scoped(doc! { "title": "milk" }), doc! { "title": "milk" });
+        assert_eq!(tasks.scoped_index(index(doc! { "tags": 1 }, None)).keys, doc! { "tags": 1 });
+    }
+}
@@ -0,0 +1,113 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::stream::StreamExt;
+use mongodb::bson::oid::ObjectId;
+use mongodb::bson::{doc, Document};
+use mongodb::options::{IndexOptions, ReturnDocument};
+use mongodb::IndexModel;
+
+use crate::models::{created_at_from_id, Role, User};
+use crate::storage::{StorageError, UserPage, UserRepository};
+use super::{get_datetime, to_bson_datetime, TenantCollection};
+
+/// User accounts in the `users` collection. A unique index on `email` turns
+/// duplicate registrations into [`StorageError::Conflict`].
+pub struct MongoUserRepository {
+    collection: TenantCollection,
+}
+
+impl MongoUserRepository {
+    pub fn new(collection: TenantCollection) -> Self {
+        MongoUserRepository { collection }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), StorageError> {
+        let email_index = IndexModel::builder()
+            .keys(doc! { "email": 1 })
+            .options(IndexOptions::builder().name("users_email".to_string()).unique(true).build())
+            .build();
+        self.collection.create_index(email_index).await?;
+        Ok(())
+    }
+
+    async fn find(&self, filter: Document) -> Result<Option<User>, StorageError> {
+        let doc = self.collection.find_one(filter).await?;
+        Ok(doc.as_ref().and_then(document_to_user))
+    }
+
+    async fn set(&self, id: ObjectId, changes: Document) -> Result<Option<User>, StorageError> {
+        let doc = self.collection
+            .find_one_and_update(doc! { "_id": id }, doc! { "$set": changes })
+            .return_document(ReturnDocument::After)
+            .await?;
+        Ok(doc.as_ref().and_then(document_to_user))
+    }
+}
+
+fn document_to_user(doc: &Document) -> Option<User> {
+    let id = doc.get_object_id("_id").ok()?;
+    Some(User {
+        id,
+        email: doc.get_str("email").ok()?.to_string(),
+        password_hash: doc.get_str("password_hash").ok()?.to_string(),
+        // Accounts from before roles existed have none.
+        role: doc.get_str("role").ok().and_then(|role| role.parse().ok()).unwrap_or_default(),
+        disabled_at: get_datetime(doc, "disabled_at"),
+        created_at: get_datetime(doc, "created_at").unwrap_or_else(|| created_at_from_id(&id)),
+    })
+}
+
+#[async_trait]
+impl UserRepository for MongoUserRepository {
+    async fn create(&self, user: &User) -> Result<(), StorageError> {
+        let doc = doc! {
+            "_id": user.id,
+            "email": &user.email,
+            "password_hash": &user.password_hash,
+            "role": user.role.as_str(),
+            "disabled_at": user.disabled_at.map(to_bson_datetime),
+            "created_at": to_bson_datetime(user.created_at),
+        };
+        self.collection.insert_one(doc).await?;
+        Ok(())
+    }
+
+    async fn get(&self, id: ObjectId) -> Result<Option<User>, StorageError> {
+        self.find(doc! { "_id": id }).await
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, StorageError> {
+        self.find(doc! { "email": email }).await
+    }
+
+    async fn list(&self, offset: u64, limit: u64) -> Result<UserPage, StorageError> {
+        let total = self.collection.count_documents(doc! {}).await?;
+        let mut cursor = self.collection
+            .find(doc! {})
+            .sort(doc! { "_id": 1 })
+            .skip(offset)
+            .limit(limit as i64)
+            .await?;
+        let mut users = Vec::new();
+
+        while let Some(result) = cursor.next().await {
+            if let Some(user) = document_to_user(&result?) {
+                users.push(user);
+            }
+        }
+
+        Ok(UserPage { users, total })
+    }
+
+    async fn set_role(&self, id: ObjectId, role: Role) -> Result<Option<User>, StorageError> {
+        self.set(id, doc! { "role": role.as_str() }).await
+    }
+
+    async fn set_disabled(
+        &self,
+        id: ObjectId,
+        disabled_at: Option<DateTime<Utc>>,
+    ) -> Result<Option<User>, StorageError> {
+        self.set(id, doc! { "disabled_at": disabled_at.map(to_bson_datetime) }).await
+    }
+}
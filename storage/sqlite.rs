@@ -0,0 +1,697 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use mongodb::bson::oid::ObjectId;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, Type, ValueRef};
+use rusqlite::{params, params_from_iter, Connection, ErrorCode, OptionalExtension, Row};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::models::{created_at_from_id, Priority, Progress, Task, TaskChanges, TaskInput};
+use crate::search::{self, SearchHit};
+use crate::subtasks::MAX_SUBTASK_DEPTH;
+use super::{
+    check_nesting, check_revision, check_tag_limit, sort_tag_counts, SortField, SortKey, StorageError, TagCount,
+    TaskFilter, TaskPage, TaskQuery, TaskRepository, TaskScope,
+};
+
+mod api_keys;
+mod idempotency;
+mod lists;
+mod refresh_tokens;
+mod revoked_tokens;
+mod users;
+
+pub use api_keys::SqliteApiKeyRepository;
+pub use idempotency::SqliteIdempotencyRepository;
+pub use lists::SqliteListRepository;
+pub use refresh_tokens::SqliteRefreshTokenRepository;
+pub use revoked_tokens::SqliteRevokedTokenRepository;
+pub use users::SqliteUserRepository;
+
+/// Schema migrations, applied in order on startup. Append new entries here;
+/// never edit a migration that has already shipped.
+const MIGRATIONS: &[(i64, &str)] = &[
+    (1, include_str!("sqlite/migrations/0001_create_tasks.sql")),
+    (2, include_str!("sqlite/migrations/0002_add_task_completion.sql")),
+    (3, include_str!("sqlite/migrations/0003_add_task_details.sql")),
+    (4, include_str!("sqlite/migrations/0004_add_task_timestamps.sql")),
+    (5, include_str!("sqlite/migrations/0005_create_idempotency_keys.sql")),
+    (6, include_str!("sqlite/migrations/0006_create_users.sql")),
+    (7, include_str!("sqlite/migrations/0007_replace_sessions_with_refresh_tokens.sql")),
+    (8, include_str!("sqlite/migrations/0008_create_api_keys.sql")),
+    (9, include_str!("sqlite/migrations/0009_add_user_roles.sql")),
+    (10, include_str!("sqlite/migrations/0010_create_lists.sql")),
+    (11, include_str!("sqlite/migrations/0011_add_task_tags.sql")),
+    (12, include_str!("sqlite/migrations/0012_add_subtasks_and_checklists.sql")),
+];
+
+const TASK_COLUMNS: &str = "id, owner_id, list_id, parent_id, title, description, completed, completed_at, due_at, \
+                            priority, tags, checklist, created_at, updated_at, revision";
+
+impl From<rusqlite::Error> for StorageError {
+    fn from(err: rusqlite::Error) -> Self {
+        match err.sqlite_error_code() {
+            Some(ErrorCode::ConstraintViolation) => StorageError::Conflict(err.to_string()),
+            _ => StorageError::Database(err.to_string()),
+        }
+    }
+}
+
+impl ToSql for Priority {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for Priority {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value.as_str()?.parse().map_err(|err: String| FromSqlError::Other(err.into()))
+    }
+}
+
+/// Handle to a SQLite database file, shared by the repositories stored in it.
+#[derive(Clone)]
+pub struct SqliteDatabase {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteDatabase {
+    /// Opens (or creates) the database at `path` and brings its schema up to date.
+    pub fn open(path: &str) -> Result<Self, StorageError> {
+        let mut conn = Connection::open(path)?;
+        migrate(&mut conn)?;
+        Ok(SqliteDatabase { conn: Arc::new(Mutex::new(conn)) })
+    }
+
+    /// Runs `f` against the connection on the blocking thread pool, since
+    /// rusqlite calls would otherwise stall the async executor.
+    async fn with_conn<T, F>(&self, f: F) -> Result<T, StorageError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T, StorageError> + Send + 'static,
+    {
+        let conn = Arc::clone(&self.conn);
+        tokio::task::spawn_blocking(move || {
+            let mut conn = conn.lock().unwrap();
+            f(&mut conn)
+        })
+        .await
+        .map_err(|err| StorageError::Database(err.to_string()))?
+    }
+}
+
+/// Task store backed by a single SQLite database file, for single-node
+/// deployments. Ids are ObjectId hex strings, as with the other backends.
+pub struct SqliteTaskRepository {
+    db: SqliteDatabase,
+}
+
+impl SqliteTaskRepository {
+    pub fn new(db: SqliteDatabase) -> Self {
+        SqliteTaskRepository { db }
+    }
+
+    /// Applies `change` to the stored task inside a transaction and records
+    /// the write, unless `change` fails. `change` can read other tasks through
+    /// the transaction.
+    async fn modify<F>(
+        &self,
+        scope: &TaskScope,
+        id: ObjectId,
+        expected_revisions: Option<Vec<i64>>,
+        change: F,
+    ) -> Result<Option<Task>, StorageError>
+    where
+        F: FnOnce(&Connection, &mut Task) -> Result<(), StorageError> + Send + 'static,
+    {
+        let scope = scope.clone();
+        self.db.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            let Some(mut task) = load_task(&tx, &scope, &id)? else {
+                return Ok(None);
+            };
+            check_revision(&task, expected_revisions.as_deref())?;
+            change(&tx, &mut task)?;
+            task.updated_at = Utc::now();
+            task.revision += 1;
+            save_task(&tx, &task)?;
+            tx.commit()?;
+            Ok(Some(task))
+        })
+        .await
+    }
+}
+
+fn migrate(conn: &mut Connection) -> Result<(), StorageError> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY NOT NULL,
+            applied_at TEXT NOT NULL
+        )",
+    )?;
+
+    let current: i64 = conn.query_row(
+        "SELECT COALESCE(MAX(version), 0) FROM schema_migrations",
+        [],
+        |row| row.get(0),
+    )?;
+
+    for (version, sql) in MIGRATIONS.iter().filter(|(version, _)| *version > current) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(sql)?;
+        tx.execute(
+            "INSERT INTO schema_migrations (version, applied_at) VALUES (?1, datetime('now'))",
+            params![version],
+        )?;
+        tx.commit()?;
+        println!("Applied SQLite migration {}", version);
+    }
+
+    Ok(())
+}
+
+fn row_to_task(row: &Row) -> rusqlite::Result<Task> {
+    let id: String = row.get("id")?;
+    let created_at = match row.get("created_at")? {
+        Some(created_at) => created_at,
+        None => ObjectId::parse_str(&id).map(|oid| created_at_from_id(&oid)).unwrap_or_default(),
+    };
+    Ok(Task {
+        id: Some(id),
+        owner_id: row.get("owner_id")?,
+        list_id: row.get("list_id")?,
+        parent_id: row.get("parent_id")?,
+        title: row.get("title")?,
+        description: row.get("description")?,
+        completed: row.get("completed")?,
+        completed_at: row.get("completed_at")?,
+        due_at: row.get("due_at")?,
+        priority: row.get("priority")?,
+        tags: json_column(row, "tags")?,
+        checklist: json_column(row, "checklist")?,
+        created_at,
+        updated_at: row.get::<_, Option<_>>("updated_at")?.unwrap_or(created_at),
+        revision: row.get("revision")?,
+        progress: None,
+    })
+}
+
+/// Tags and checklists are stored as JSON arrays.
+fn json_column<T: DeserializeOwned>(row: &Row, column: &str) -> rusqlite::Result<T> {
+    let index = row.as_ref().column_index(column)?;
+    let value: String = row.get(index)?;
+    serde_json::from_str(&value).map_err(|err| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, err.into()))
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String, StorageError> {
+    serde_json::to_string(value).map_err(|err| StorageError::Database(err.to_string()))
+}
+
+fn load_task(conn: &Connection, scope: &TaskScope, id: &ObjectId) -> Result<Option<Task>, StorageError> {
+    let (conditions, mut values) = scope_clause(scope);
+    values.push(Box::new(id.to_hex()));
+    let task = conn
+        .query_row(
+            &format!("SELECT {} FROM tasks WHERE ({}) AND id = ?", TASK_COLUMNS, conditions),
+            params_from_iter(&values),
+            row_to_task,
+        )
+        .optional()?;
+    Ok(task)
+}
+
+fn insert_task(conn: &Connection, task: &Task) -> Result<(), StorageError> {
+    conn.execute(
+        &format!(
+            "INSERT INTO tasks ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+            TASK_COLUMNS
+        ),
+        params![
+            task.id,
+            task.owner_id,
+            task.list_id,
+            task.parent_id,
+            task.title,
+            task.description,
+            task.completed,
+            task.completed_at,
+            task.due_at,
+            task.priority,
+            to_json(&task.tags)?,
+            to_json(&task.checklist)?,
+            task.created_at,
+            task.updated_at,
+            task.revision,
+        ],
+    )?;
+    Ok(())
+}
+
+/// `parent` and the tasks above it, no more than one past the deepest nesting
+/// allowed.
+fn ancestors(conn: &Connection, parent: &ObjectId) -> Result<Vec<ObjectId>, StorageError> {
+    let mut stmt = conn.prepare(
+        "WITH RECURSIVE ancestors (id, depth) AS (
+             SELECT ?1, 1
+             UNION SELECT tasks.parent_id, ancestors.depth + 1 FROM tasks JOIN ancestors ON tasks.id = ancestors.id
+             WHERE tasks.parent_id IS NOT NULL AND ancestors.depth <= ?2
+         )
+         SELECT DISTINCT id FROM ancestors",
+    )?;
+    let ids = stmt
+        .query_map(params![parent.to_hex(), MAX_SUBTASK_DEPTH as i64], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(ids.iter().filter_map(|id| ObjectId::parse_str(id).ok()).collect())
+}
+
+/// Levels of subtasks below task `id`, counted no further than one past the
+/// deepest nesting allowed.
+fn height(conn: &Connection, id: &ObjectId) -> Result<usize, StorageError> {
+    let height: i64 = conn.query_row(
+        "WITH RECURSIVE subtasks (id, depth) AS (
+             SELECT ?1, 0
+             UNION SELECT tasks.id, subtasks.depth + 1 FROM tasks JOIN subtasks ON tasks.parent_id = subtasks.id
+             WHERE subtasks.depth <= ?2
+         )
+         SELECT MAX(depth) FROM subtasks",
+        params![id.to_hex(), MAX_SUBTASK_DEPTH as i64],
+        |row| row.get(0),
+    )?;
+    Ok(height as usize)
+}
+
+fn save_task(conn: &Connection, task: &Task) -> Result<(), StorageError> {
+    conn.execute(
+        "UPDATE tasks SET parent_id = ?2, title = ?3, description = ?4, completed = ?5, completed_at = ?6,
+             due_at = ?7, priority = ?8, tags = ?9, checklist = ?10, created_at = ?11, updated_at = ?12,
+             revision = ?13
+         WHERE id = ?1",
+        params![
+            task.id,
+            task.parent_id,
+            task.title,
+            task.description,
+            task.completed,
+            task.completed_at,
+            task.due_at,
+            task.priority,
+            to_json(&task.tags)?,
+            to_json(&task.checklist)?,
+            task.created_at,
+            task.updated_at,
+            task.revision,
+        ],
+    )?;
+    Ok(())
+}
+
+type SqlValues = Vec<Box<dyn ToSql + Send>>;
+
+/// Translates the scope into a `WHERE` condition and its parameters.
+fn scope_clause(scope: &TaskScope) -> (String, SqlValues) {
+    let mut branches = Vec::new();
+    let mut values: SqlValues = Vec::new();
+
+    if let Some(owner) = scope.owner {
+        branches.push("(owner_id = ? AND list_id IS NULL)".to_string());
+        values.push(Box::new(owner.to_hex()));
+    }
+    if scope.unowned {
+        branches.push("(owner_id IS NULL AND list_id IS NULL)".to_string());
+    }
+    if !scope.lists.is_empty() {
+        branches.push(format!("list_id IN ({})", vec!["?"; scope.lists.len()].join(", ")));
+        for list in &scope.lists {
+            values.push(Box::new(list.to_hex()));
+        }
+    }
+
+    if branches.is_empty() {
+        return ("0".to_string(), values);
+    }
+    (branches.join(" OR "), values)
+}
+
+/// Translates the filter into a `WHERE` condition and its parameters,
+/// restricted to the tasks in `scope`.
+fn filter_clause(scope: &TaskScope, filter: &TaskFilter) -> (String, SqlValues) {
+    let (scope_conditions, mut values) = scope_clause(scope);
+    let mut conditions = vec![format!("({})", scope_conditions)];
+
+    if let Some(completed) = filter.completed {
+        conditions.push("completed = ?".to_string());
+        values.push(Box::new(completed));
+    }
+    if !filter.priorities.is_empty() {
+        let placeholders = vec!["?"; filter.priorities.len()].join(", ");
+        conditions.push(format!("priority IN ({})", placeholders));
+        for priority in &filter.priorities {
+            values.push(Box::new(*priority));
+        }
+    }
+    if let Some(before) = filter.due_before {
+        conditions.push("due_at < ?".to_string());
+        values.push(Box::new(before));
+    }
+    if let Some(after) = filter.due_after {
+        conditions.push("due_at > ?".to_string());
+        values.push(Box::new(after));
+    }
+    for tag in &filter.tags {
+        conditions.push("EXISTS (SELECT 1 FROM json_each(tasks.tags) WHERE json_each.value = ?)".to_string());
+        values.push(Box::new(tag.clone()));
+    }
+    match filter.parent {
+        Some(Some(parent)) => {
+            conditions.push("parent_id = ?".to_string());
+            values.push(Box::new(parent.to_hex()));
+        }
+        Some(None) => conditions.push("parent_id IS NULL".to_string()),
+        None => {}
+    }
+
+    (conditions.join(" AND "), values)
+}
+
+fn order_clause(sort: &[SortKey]) -> String {
+    let mut terms: Vec<String> = sort
+        .iter()
+        .map(|key| {
+            let column = match key.field {
+                SortField::Title => "title",
+                SortField::Priority => {
+                    "CASE priority WHEN 'low' THEN 0 WHEN 'medium' THEN 1 WHEN 'high' THEN 2 WHEN 'urgent' THEN 3 ELSE -1 END"
+                }
+                SortField::DueAt => "due_at",
+                // Ids start with the creation time and, unlike `created_at`,
+                // are set on rows from before that column existed.
+                SortField::CreatedAt => "id",
+                SortField::UpdatedAt => "updated_at",
+                SortField::Completed => "completed",
+            };
+            format!("{} {}", column, if key.descending { "DESC" } else { "ASC" })
+        })
+        .collect();
+    terms.push("id ASC".to_string());
+    terms.join(", ")
+}
+
+#[async_trait]
+impl TaskRepository for SqliteTaskRepository {
+    async fn list(&self, scope: &TaskScope, query: &TaskQuery) -> Result<TaskPage, StorageError> {
+        let (conditions, mut values) = filter_clause(scope, &query.filter);
+        let count_sql = format!("SELECT COUNT(*) FROM tasks WHERE {}", conditions);
+        let count_values = values.len();
+
+        let mut select_sql = format!("SELECT {} FROM tasks WHERE {}", TASK_COLUMNS, conditions);
+        if let Some(after) = query.after {
+            select_sql.push_str(" AND id > ?");
+            values.push(Box::new(after.to_hex()));
+        }
+        select_sql.push_str(&format!(" ORDER BY {} LIMIT ? OFFSET ?", order_clause(&query.sort)));
+        values.push(Box::new(query.limit as i64 + 1));
+        values.push(Box::new(query.offset as i64));
+
+        let (tasks, total) = self.db.with_conn(move |conn| {
+            let total: i64 = conn.query_row(
+                &count_sql,
+                params_from_iter(&values[..count_values]),
+                |row| row.get(0),
+            )?;
+            let mut stmt = conn.prepare(&select_sql)?;
+            let tasks = stmt
+                .query_map(params_from_iter(&values), row_to_task)?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok((tasks, total as u64))
+        })
+        .await?;
+        Ok(TaskPage::from_overfetch(tasks, query, total))
+    }
+
+    async fn search(&self, scope: &TaskScope, terms: &[String], limit: u64) -> Result<Vec<SearchHit>, StorageError> {
+        // LIKE narrows the rows down to possible matches; ranking happens in
+        // `search::rank`, shared with the in-memory backend.
+        // Terms are alphanumeric, so they need no escaping inside a pattern.
+        // The scope's parameters come first, so the terms are numbered after them.
+        let (scope_conditions, mut values) = scope_clause(scope);
+        let first = values.len() + 1;
+        let conditions: Vec<String> = (first..first + terms.len())
+            .map(|index| format!("title LIKE ?{0} OR description LIKE ?{0}", index))
+            .collect();
+        let sql = format!(
+            "SELECT {} FROM tasks WHERE ({}) AND ({})",
+            TASK_COLUMNS,
+            scope_conditions,
+            conditions.join(" OR ")
+        );
+        for term in terms {
+            values.push(Box::new(format!("%{}%", term)));
+        }
+        let terms = terms.to_vec();
+
+        self.db.with_conn(move |conn| {
+            let mut stmt = conn.prepare(&sql)?;
+            let candidates = stmt
+                .query_map(params_from_iter(&values), row_to_task)?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(search::rank(candidates, &terms, limit))
+        })
+        .await
+    }
+
+    async fn get(&self, scope: &TaskScope, id: ObjectId) -> Result<Option<Task>, StorageError> {
+        let scope = scope.clone();
+        self.db.with_conn(move |conn| load_task(conn, &scope, &id)).await
+    }
+
+    async fn create(&self, owner: ObjectId, list: Option<ObjectId>, input: TaskInput) -> Result<Task, StorageError> {
+        let task = input.into_task(ObjectId::new(), owner, list, Utc::now());
+        self.db.with_conn(move |conn| {
+            insert_task(conn, &task)?;
+            Ok(task)
+        })
+        .await
+    }
+
+    async fn create_many(
+        &self,
+        owner: ObjectId,
+        list: Option<ObjectId>,
+        inputs: Vec<TaskInput>,
+    ) -> Vec<Result<Task, StorageError>> {
+        let now = Utc::now();
+        let count = inputs.len();
+        let tasks: Vec<Task> =
+            inputs.into_iter().map(|input| input.into_task(ObjectId::new(), owner, list, now)).collect();
+        let stored = self.db.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            for task in &tasks {
+                insert_task(&tx, task)?;
+            }
+            tx.commit()?;
+            Ok(tasks)
+        })
+        .await;
+        match stored {
+            Ok(tasks) => tasks.into_iter().map(Ok).collect(),
+            Err(err) => vec![Err(err); count],
+        }
+    }
+
+    async fn update(
+        &self,
+        scope: &TaskScope,
+        id: ObjectId,
+        changes: TaskChanges,
+        expected_revisions: Option<Vec<i64>>,
+    ) -> Result<Option<Task>, StorageError> {
+        self.modify(scope, id, expected_revisions, move |conn, existing| {
+            if let Some(Some(parent)) = changes.parent_id {
+                check_nesting(id, &ancestors(conn, &parent)?, height(conn, &id)?)?;
+            }
+            existing.apply(changes, Utc::now());
+            Ok(())
+        })
+        .await
+    }
+
+    async fn delete(
+        &self,
+        scope: &TaskScope,
+        id: ObjectId,
+        expected_revisions: Option<Vec<i64>>,
+    ) -> Result<bool, StorageError> {
+        let scope = scope.clone();
+        self.db.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            let Some(task) = load_task(&tx, &scope, &id)? else {
+                return Ok(false);
+            };
+            check_revision(&task, expected_revisions.as_deref())?;
+            tx.execute("DELETE FROM tasks WHERE id = ?1", params![id.to_hex()])?;
+            tx.execute(
+                "UPDATE tasks SET parent_id = NULL, updated_at = ?2, revision = revision + 1 WHERE parent_id = ?1",
+                params![id.to_hex(), Utc::now()],
+            )?;
+            tx.commit()?;
+            Ok(true)
+        })
+        .await
+    }
+
+    async fn delete_matching(&self, scope: &TaskScope, filter: &TaskFilter) -> Result<u64, StorageError> {
+        let (conditions, values) = filter_clause(scope, filter);
+        let select_sql = format!("SELECT id FROM tasks WHERE {}", conditions);
+        let delete_sql = format!("DELETE FROM tasks WHERE {}", conditions);
+        self.db.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            // The ids are collected first so that their subtasks can be found;
+            // detaching those before the delete could make them match it.
+            let deleted = tx
+                .prepare(&select_sql)?
+                .query_map(params_from_iter(&values), |row| row.get::<_, String>(0))?
+                .collect::<Result<Vec<_>, _>>()?;
+            tx.execute(&delete_sql, params_from_iter(&values))?;
+            tx.execute(
+                "UPDATE tasks SET parent_id = NULL, updated_at = ?2, revision = revision + 1
+                 WHERE parent_id IN (SELECT value FROM json_each(?1))",
+                params![to_json(&deleted)?, Utc::now()],
+            )?;
+            tx.commit()?;
+            Ok(deleted.len() as u64)
+        })
+        .await
+    }
+
+    async fn add_tags(&self, scope: &TaskScope, id: ObjectId, tags: &[String]) -> Result<Option<Task>, StorageError> {
+        let tags = tags.to_vec();
+        self.modify(scope, id, None, move |_, existing| {
+            check_tag_limit(existing, &tags)?;
+            existing.add_tags(&tags);
+            Ok(())
+        })
+        .await
+    }
+
+    async fn remove_tags(
+        &self,
+        scope: &TaskScope,
+        id: ObjectId,
+        tags: &[String],
+    ) -> Result<Option<Task>, StorageError> {
+        let tags = tags.to_vec();
+        self.modify(scope, id, None, move |_, existing| {
+            existing.remove_tags(&tags);
+            Ok(())
+        })
+        .await
+    }
+
+    async fn tag_counts(&self, scope: &TaskScope) -> Result<Vec<TagCount>, StorageError> {
+        let (conditions, values) = scope_clause(scope);
+        let sql = format!(
+            "SELECT tag.value, COUNT(*) FROM tasks, json_each(tasks.tags) AS tag WHERE ({}) GROUP BY tag.value",
+            conditions
+        );
+        let mut counts = self.db.with_conn(move |conn| {
+            let mut stmt = conn.prepare(&sql)?;
+            let counts = stmt
+                .query_map(params_from_iter(&values), |row| {
+                    Ok(TagCount { name: row.get(0)?, count: row.get::<_, i64>(1)? as u64 })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(counts)
+        })
+        .await?;
+        sort_tag_counts(&mut counts);
+        Ok(counts)
+    }
+
+    async fn rename_tags(&self, scope: &TaskScope, from: &[String], to: &str) -> Result<u64, StorageError> {
+        let (conditions, mut values) = scope_clause(scope);
+        let sql = format!(
+            "SELECT {} FROM tasks WHERE ({}) AND EXISTS \
+             (SELECT 1 FROM json_each(tasks.tags) WHERE json_each.value IN ({}))",
+            TASK_COLUMNS,
+            conditions,
+            vec!["?"; from.len()].join(", ")
+        );
+        for tag in from {
+            values.push(Box::new(tag.clone()));
+        }
+        let from = from.to_vec();
+        let to = to.to_string();
+
+        self.db.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            let tasks = tx
+                .prepare(&sql)?
+                .query_map(params_from_iter(&values), row_to_task)?
+                .collect::<Result<Vec<_>, _>>()?;
+            let now = Utc::now();
+            let mut renamed = 0;
+            for mut task in tasks {
+                if task.rename_tags(&from, &to) {
+                    task.updated_at = now;
+                    task.revision += 1;
+                    save_task(&tx, &task)?;
+                    renamed += 1;
+                }
+            }
+            tx.commit()?;
+            Ok(renamed)
+        })
+        .await
+    }
+
+    async fn subtask_progress(&self, parents: &[ObjectId]) -> Result<HashMap<String, Progress>, StorageError> {
+        if parents.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let sql = format!(
+            "SELECT parent_id, SUM(completed), COUNT(*) FROM tasks WHERE parent_id IN ({}) GROUP BY parent_id",
+            vec!["?"; parents.len()].join(", ")
+        );
+        let parents: Vec<String> = parents.iter().map(|parent| parent.to_hex()).collect();
+
+        self.db.with_conn(move |conn| {
+            let mut stmt = conn.prepare(&sql)?;
+            let progress = stmt
+                .query_map(params_from_iter(&parents), |row| {
+                    let progress = Progress { done: row.get::<_, i64>(1)? as u64, total: row.get::<_, i64>(2)? as u64 };
+                    Ok((row.get(0)?, progress))
+                })?
+                .collect::<Result<HashMap<_, _>, _>>()?;
+            Ok(progress)
+        })
+        .await
+    }
+
+    async fn transfer(&self, id: ObjectId, new_owner: ObjectId) -> Result<Option<Task>, StorageError> {
+        self.db.with_conn(move |conn| {
+            let updated = conn.execute(
+                "WITH RECURSIVE moved (id) AS (
+                     SELECT id FROM tasks WHERE id = ?1
+                     UNION SELECT tasks.id FROM tasks JOIN moved ON tasks.parent_id = moved.id
+                 )
+                 UPDATE tasks SET owner_id = ?2, updated_at = ?3, revision = revision + 1,
+                     parent_id = CASE WHEN id = ?1 THEN NULL ELSE parent_id END
+                 WHERE id IN (SELECT id FROM moved)",
+                params![id.to_hex(), new_owner.to_hex(), Utc::now()],
+            )?;
+            if updated == 0 {
+                return Ok(None);
+            }
+            let task = conn.query_row(
+                &format!("SELECT {} FROM tasks WHERE id = ?1", TASK_COLUMNS),
+                params![id.to_hex()],
+                row_to_task,
+            )?;
+            Ok(Some(task))
+        })
+        .await
+    }
+}
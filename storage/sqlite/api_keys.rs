@@ -0,0 +1,125 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use rusqlite::types::Type;
+use rusqlite::{params, OptionalExtension, Row};
+
+use crate::models::{ApiKey, Scope};
+use crate::storage::{ApiKeyRepository, StorageError};
+use super::SqliteDatabase;
+
+const API_KEY_COLUMNS: &str = "id, user_id, name, prefix, key_hash, scopes, created_at, last_used_at";
+
+/// API keys in the `api_keys` table, with their scopes stored space-separated.
+pub struct SqliteApiKeyRepository {
+    db: SqliteDatabase,
+}
+
+impl SqliteApiKeyRepository {
+    pub fn new(db: SqliteDatabase) -> Self {
+        SqliteApiKeyRepository { db }
+    }
+}
+
+fn conversion_error(column: usize, err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> rusqlite::Error {
+    rusqlite::Error::FromSqlConversionFailure(column, Type::Text, err.into())
+}
+
+fn parse_id(row: &Row, column: &str) -> rusqlite::Result<ObjectId> {
+    let index = row.as_ref().column_index(column)?;
+    let id: String = row.get(index)?;
+    ObjectId::parse_str(&id).map_err(|err| conversion_error(index, err))
+}
+
+fn row_to_api_key(row: &Row) -> rusqlite::Result<ApiKey> {
+    let index = row.as_ref().column_index("scopes")?;
+    let scopes: String = row.get(index)?;
+    let scopes = scopes
+        .split_whitespace()
+        .map(|scope| scope.parse::<Scope>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| conversion_error(index, err))?;
+    Ok(ApiKey {
+        id: parse_id(row, "id")?,
+        user_id: parse_id(row, "user_id")?,
+        name: row.get("name")?,
+        prefix: row.get("prefix")?,
+        key_hash: row.get("key_hash")?,
+        scopes,
+        created_at: row.get("created_at")?,
+        last_used_at: row.get("last_used_at")?,
+    })
+}
+
+#[async_trait]
+impl ApiKeyRepository for SqliteApiKeyRepository {
+    async fn create(&self, key: &ApiKey) -> Result<(), StorageError> {
+        let key = key.clone();
+        let scopes = key.scopes.iter().map(Scope::as_str).collect::<Vec<_>>().join(" ");
+        self.db.with_conn(move |conn| {
+            conn.execute(
+                &format!("INSERT INTO api_keys ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)", API_KEY_COLUMNS),
+                params![
+                    key.id.to_hex(),
+                    key.user_id.to_hex(),
+                    key.name,
+                    key.prefix,
+                    key.key_hash,
+                    scopes,
+                    key.created_at,
+                    key.last_used_at,
+                ],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn list(&self, user_id: ObjectId) -> Result<Vec<ApiKey>, StorageError> {
+        self.db.with_conn(move |conn| {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {} FROM api_keys WHERE user_id = ?1 ORDER BY id",
+                API_KEY_COLUMNS
+            ))?;
+            let keys = stmt
+                .query_map(params![user_id.to_hex()], row_to_api_key)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(keys)
+        })
+        .await
+    }
+
+    async fn find_by_hash(&self, key_hash: &str) -> Result<Option<ApiKey>, StorageError> {
+        let key_hash = key_hash.to_string();
+        self.db.with_conn(move |conn| {
+            let key = conn
+                .query_row(
+                    &format!("SELECT {} FROM api_keys WHERE key_hash = ?1", API_KEY_COLUMNS),
+                    params![key_hash],
+                    row_to_api_key,
+                )
+                .optional()?;
+            Ok(key)
+        })
+        .await
+    }
+
+    async fn touch(&self, id: ObjectId, at: DateTime<Utc>) -> Result<(), StorageError> {
+        self.db.with_conn(move |conn| {
+            conn.execute("UPDATE api_keys SET last_used_at = ?2 WHERE id = ?1", params![id.to_hex(), at])?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn revoke(&self, user_id: ObjectId, id: ObjectId) -> Result<bool, StorageError> {
+        self.db.with_conn(move |conn| {
+            let deleted = conn.execute(
+                "DELETE FROM api_keys WHERE id = ?1 AND user_id = ?2",
+                params![id.to_hex(), user_id.to_hex()],
+            )?;
+            Ok(deleted > 0)
+        })
+        .await
+    }
+}
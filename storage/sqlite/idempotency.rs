@@ -0,0 +1,82 @@
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use rusqlite::{params, OptionalExtension};
+
+use crate::storage::{IdempotencyRecord, IdempotencyRepository, StorageError, StoredResponse};
+use super::SqliteDatabase;
+
+/// Idempotency keys in the `idempotency_keys` table. Expired rows are purged
+/// whenever a key is claimed.
+pub struct SqliteIdempotencyRepository {
+    db: SqliteDatabase,
+    ttl: Duration,
+}
+
+impl SqliteIdempotencyRepository {
+    pub fn new(db: SqliteDatabase, ttl: Duration) -> Self {
+        SqliteIdempotencyRepository { db, ttl }
+    }
+}
+
+fn to_storage_error(err: serde_json::Error) -> StorageError {
+    StorageError::Database(err.to_string())
+}
+
+#[async_trait]
+impl IdempotencyRepository for SqliteIdempotencyRepository {
+    async fn claim(&self, key: &str, request_hash: &str) -> Result<Option<IdempotencyRecord>, StorageError> {
+        let key = key.to_string();
+        let request_hash = request_hash.to_string();
+        let now = Utc::now();
+        let cutoff = now - self.ttl;
+
+        self.db.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            tx.execute("DELETE FROM idempotency_keys WHERE created_at < ?1", params![cutoff])?;
+            let inserted = tx.execute(
+                "INSERT OR IGNORE INTO idempotency_keys (key, request_hash, created_at) VALUES (?1, ?2, ?3)",
+                params![key, request_hash, now],
+            )?;
+            let existing = if inserted > 0 {
+                None
+            } else {
+                tx.query_row(
+                    "SELECT request_hash, response FROM idempotency_keys WHERE key = ?1",
+                    params![key],
+                    |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?)),
+                )
+                .optional()?
+            };
+            tx.commit()?;
+
+            let Some((request_hash, response)) = existing else {
+                return Ok(None);
+            };
+            let response = response
+                .map(|response| serde_json::from_str(&response))
+                .transpose()
+                .map_err(to_storage_error)?;
+            Ok(Some(IdempotencyRecord { request_hash, response }))
+        })
+        .await
+    }
+
+    async fn complete(&self, key: &str, response: &StoredResponse) -> Result<(), StorageError> {
+        let key = key.to_string();
+        let response = serde_json::to_string(response).map_err(to_storage_error)?;
+        self.db.with_conn(move |conn| {
+            conn.execute("UPDATE idempotency_keys SET response = ?2 WHERE key = ?1", params![key, response])?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn release(&self, key: &str) -> Result<(), StorageError> {
+        let key = key.to_string();
+        self.db.with_conn(move |conn| {
+            conn.execute("DELETE FROM idempotency_keys WHERE key = ?1", params![key])?;
+            Ok(())
+        })
+        .await
+    }
+}
@@ -0,0 +1,196 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use mongodb::bson::oid::ObjectId;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, Type, ValueRef};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+
+use crate::models::{ListMember, ListRole, TaskList};
+use crate::storage::{no_owner_left, ListRepository, StorageError};
+use super::SqliteDatabase;
+
+/// Shared lists in the `lists` table, with their members in `list_members`.
+pub struct SqliteListRepository {
+    db: SqliteDatabase,
+}
+
+impl SqliteListRepository {
+    pub fn new(db: SqliteDatabase) -> Self {
+        SqliteListRepository { db }
+    }
+
+    /// Runs `change` and bumps `updated_at` in one transaction, returning the
+    /// updated list. The transaction is rolled back if the change left the
+    /// list without an owner.
+    async fn modify<F>(&self, id: ObjectId, change: F) -> Result<Option<TaskList>, StorageError>
+    where
+        F: FnOnce(&Connection) -> rusqlite::Result<()> + Send + 'static,
+    {
+        self.db.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            let updated =
+                tx.execute("UPDATE lists SET updated_at = ?2 WHERE id = ?1", params![id.to_hex(), Utc::now()])?;
+            if updated == 0 {
+                return Ok(None);
+            }
+            change(&tx)?;
+            let owners: i64 = tx.query_row(
+                "SELECT COUNT(*) FROM list_members WHERE list_id = ?1 AND role = ?2",
+                params![id.to_hex(), ListRole::Owner],
+                |row| row.get(0),
+            )?;
+            if owners == 0 {
+                return Err(no_owner_left());
+            }
+            let list = load_list(&tx, &id)?;
+            tx.commit()?;
+            Ok(list)
+        })
+        .await
+    }
+}
+
+impl ToSql for ListRole {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for ListRole {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value.as_str()?.parse().map_err(|err: String| FromSqlError::Other(err.into()))
+    }
+}
+
+fn parse_id(row: &Row, column: &str) -> rusqlite::Result<ObjectId> {
+    let index = row.as_ref().column_index(column)?;
+    let id: String = row.get(index)?;
+    ObjectId::parse_str(&id).map_err(|err| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(err)))
+}
+
+fn load_members(conn: &Connection, id: &ObjectId) -> rusqlite::Result<Vec<ListMember>> {
+    let mut stmt = conn.prepare("SELECT user_id, role FROM list_members WHERE list_id = ?1 ORDER BY rowid")?;
+    let members = stmt
+        .query_map(params![id.to_hex()], |row| {
+            Ok(ListMember { user_id: parse_id(row, "user_id")?, role: row.get("role")? })
+        })?
+        .collect();
+    members
+}
+
+fn load_list(conn: &Connection, id: &ObjectId) -> rusqlite::Result<Option<TaskList>> {
+    let list = conn
+        .query_row(
+            "SELECT id, name, created_at, updated_at FROM lists WHERE id = ?1",
+            params![id.to_hex()],
+            |row| {
+                Ok(TaskList {
+                    id: parse_id(row, "id")?,
+                    name: row.get("name")?,
+                    members: Vec::new(),
+                    created_at: row.get("created_at")?,
+                    updated_at: row.get("updated_at")?,
+                })
+            },
+        )
+        .optional()?;
+    match list {
+        Some(mut list) => {
+            list.members = load_members(conn, id)?;
+            Ok(Some(list))
+        }
+        None => Ok(None),
+    }
+}
+
+#[async_trait]
+impl ListRepository for SqliteListRepository {
+    async fn create(&self, list: &TaskList) -> Result<(), StorageError> {
+        let list = list.clone();
+        self.db.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            tx.execute(
+                "INSERT INTO lists (id, name, created_at, updated_at) VALUES (?1, ?2, ?3, ?4)",
+                params![list.id.to_hex(), list.name, list.created_at, list.updated_at],
+            )?;
+            for member in &list.members {
+                tx.execute(
+                    "INSERT INTO list_members (list_id, user_id, role) VALUES (?1, ?2, ?3)",
+                    params![list.id.to_hex(), member.user_id.to_hex(), member.role],
+                )?;
+            }
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get(&self, id: ObjectId) -> Result<Option<TaskList>, StorageError> {
+        self.db.with_conn(move |conn| Ok(load_list(conn, &id)?)).await
+    }
+
+    async fn for_member(&self, user_id: ObjectId) -> Result<Vec<TaskList>, StorageError> {
+        self.db.with_conn(move |conn| {
+            let mut stmt = conn.prepare("SELECT list_id FROM list_members WHERE user_id = ?1 ORDER BY list_id")?;
+            let ids = stmt
+                .query_map(params![user_id.to_hex()], |row| parse_id(row, "list_id"))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            let mut lists = Vec::with_capacity(ids.len());
+            for id in ids {
+                if let Some(list) = load_list(conn, &id)? {
+                    lists.push(list);
+                }
+            }
+            Ok(lists)
+        })
+        .await
+    }
+
+    async fn rename(&self, id: ObjectId, name: &str) -> Result<Option<TaskList>, StorageError> {
+        let name = name.to_string();
+        self.modify(id, move |conn| {
+            conn.execute("UPDATE lists SET name = ?2 WHERE id = ?1", params![id.to_hex(), name])?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn set_member(
+        &self,
+        id: ObjectId,
+        user_id: ObjectId,
+        role: ListRole,
+    ) -> Result<Option<TaskList>, StorageError> {
+        self.modify(id, move |conn| {
+            conn.execute(
+                "INSERT INTO list_members (list_id, user_id, role) VALUES (?1, ?2, ?3)
+                 ON CONFLICT (list_id, user_id) DO UPDATE SET role = excluded.role",
+                params![id.to_hex(), user_id.to_hex(), role],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn remove_member(&self, id: ObjectId, user_id: ObjectId) -> Result<Option<TaskList>, StorageError> {
+        self.modify(id, move |conn| {
+            conn.execute(
+                "DELETE FROM list_members WHERE list_id = ?1 AND user_id = ?2",
+                params![id.to_hex(), user_id.to_hex()],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn delete(&self, id: ObjectId) -> Result<bool, StorageError> {
+        self.db.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            tx.execute("DELETE FROM tasks WHERE list_id = ?1", params![id.to_hex()])?;
+            tx.execute("DELETE FROM list_members WHERE list_id = ?1", params![id.to_hex()])?;
+            let deleted = tx.execute("DELETE FROM lists WHERE id = ?1", params![id.to_hex()])?;
+            tx.commit()?;
+            Ok(deleted > 0)
+        })
+        .await
+    }
+}
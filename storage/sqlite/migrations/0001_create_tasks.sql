@@ -0,0 +1,4 @@
+CREATE TABLE tasks (
+    id TEXT PRIMARY KEY NOT NULL,
+    title TEXT NOT NULL
+);
@@ -0,0 +1,2 @@
+ALTER TABLE tasks ADD COLUMN completed INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE tasks ADD COLUMN completed_at TEXT;
@@ -0,0 +1,3 @@
+ALTER TABLE tasks ADD COLUMN created_at TEXT;
+ALTER TABLE tasks ADD COLUMN updated_at TEXT;
+ALTER TABLE tasks ADD COLUMN revision INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,9 @@
+CREATE TABLE idempotency_keys (
+    key TEXT PRIMARY KEY NOT NULL,
+    request_hash TEXT NOT NULL,
+    -- JSON encoded StoredResponse, NULL until the request has completed
+    response TEXT,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX idempotency_keys_created_at ON idempotency_keys (created_at);
@@ -0,0 +1,20 @@
+CREATE TABLE users (
+    id TEXT PRIMARY KEY NOT NULL,
+    email TEXT NOT NULL UNIQUE,
+    password_hash TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE TABLE sessions (
+    token_hash TEXT PRIMARY KEY NOT NULL,
+    user_id TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL
+);
+
+CREATE INDEX sessions_expires_at ON sessions (expires_at);
+
+-- Tasks created before accounts existed keep a NULL owner.
+ALTER TABLE tasks ADD COLUMN owner_id TEXT;
+
+CREATE INDEX tasks_owner_id ON tasks (owner_id, id);
@@ -0,0 +1,21 @@
+CREATE TABLE lists (
+    id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE TABLE list_members (
+    list_id TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    -- 'viewer', 'editor' or 'owner'
+    role TEXT NOT NULL,
+    PRIMARY KEY (list_id, user_id)
+);
+
+CREATE INDEX list_members_user_id ON list_members (user_id);
+
+-- Tasks in no list stay personal to their owner.
+ALTER TABLE tasks ADD COLUMN list_id TEXT;
+
+CREATE INDEX tasks_list_id ON tasks (list_id, id);
@@ -0,0 +1,2 @@
+-- JSON array of tag names, searched with json_each.
+ALTER TABLE tasks ADD COLUMN tags TEXT NOT NULL DEFAULT '[]';
@@ -0,0 +1,6 @@
+ALTER TABLE tasks ADD COLUMN parent_id TEXT;
+
+-- JSON array of checklist items, in order.
+ALTER TABLE tasks ADD COLUMN checklist TEXT NOT NULL DEFAULT '[]';
+
+CREATE INDEX tasks_parent_id ON tasks (parent_id);
@@ -0,0 +1,116 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::storage::{RefreshToken, RefreshTokenRepository, Rotation, StorageError};
+use super::SqliteDatabase;
+
+/// Refresh tokens in the `refresh_tokens` table. Expired rows are purged
+/// whenever a new token is stored.
+pub struct SqliteRefreshTokenRepository {
+    db: SqliteDatabase,
+}
+
+impl SqliteRefreshTokenRepository {
+    pub fn new(db: SqliteDatabase) -> Self {
+        SqliteRefreshTokenRepository { db }
+    }
+}
+
+fn insert_token(conn: &Connection, token: &RefreshToken) -> Result<(), StorageError> {
+    conn.execute("DELETE FROM refresh_tokens WHERE expires_at <= ?1", params![Utc::now()])?;
+    conn.execute(
+        "INSERT INTO refresh_tokens (token_hash, session_id, user_id, created_at, expires_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            token.token_hash,
+            token.session_id.to_hex(),
+            token.user_id.to_hex(),
+            token.created_at,
+            token.expires_at,
+        ],
+    )?;
+    Ok(())
+}
+
+fn parse_id(id: &str) -> Result<ObjectId, StorageError> {
+    ObjectId::parse_str(id).map_err(|err| StorageError::Database(err.to_string()))
+}
+
+#[async_trait]
+impl RefreshTokenRepository for SqliteRefreshTokenRepository {
+    async fn create(&self, token: &RefreshToken) -> Result<(), StorageError> {
+        let token = token.clone();
+        self.db.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            insert_token(&tx, &token)?;
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn rotate(
+        &self,
+        token_hash: &str,
+        replacement_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Rotation, StorageError> {
+        let token_hash = token_hash.to_string();
+        let replacement_hash = replacement_hash.to_string();
+        self.db.with_conn(move |conn| {
+            let now = Utc::now();
+            let tx = conn.transaction()?;
+            let current = tx
+                .query_row(
+                    "SELECT session_id, user_id, rotated_at IS NOT NULL FROM refresh_tokens
+                     WHERE token_hash = ?1 AND expires_at > ?2",
+                    params![token_hash, now],
+                    |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, bool>(2)?)),
+                )
+                .optional()?;
+            let Some((session_id, user_id, rotated)) = current else {
+                return Ok(Rotation::Invalid);
+            };
+
+            if rotated {
+                tx.execute("DELETE FROM refresh_tokens WHERE session_id = ?1", params![session_id])?;
+                tx.commit()?;
+                return Ok(Rotation::Reused);
+            }
+
+            tx.execute(
+                "UPDATE refresh_tokens SET rotated_at = ?2 WHERE token_hash = ?1",
+                params![token_hash, now],
+            )?;
+            let replacement = RefreshToken {
+                token_hash: replacement_hash,
+                session_id: parse_id(&session_id)?,
+                user_id: parse_id(&user_id)?,
+                created_at: now,
+                expires_at,
+            };
+            insert_token(&tx, &replacement)?;
+            tx.commit()?;
+            Ok(Rotation::Rotated(replacement))
+        })
+        .await
+    }
+
+    async fn revoke_session(&self, session_id: ObjectId) -> Result<(), StorageError> {
+        self.db.with_conn(move |conn| {
+            conn.execute("DELETE FROM refresh_tokens WHERE session_id = ?1", params![session_id.to_hex()])?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn revoke_user(&self, user_id: ObjectId) -> Result<(), StorageError> {
+        self.db.with_conn(move |conn| {
+            conn.execute("DELETE FROM refresh_tokens WHERE user_id = ?1", params![user_id.to_hex()])?;
+            Ok(())
+        })
+        .await
+    }
+}
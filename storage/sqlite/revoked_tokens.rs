@@ -0,0 +1,49 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rusqlite::params;
+
+use crate::storage::{RevokedTokenRepository, StorageError};
+use super::SqliteDatabase;
+
+/// Access token denylist in the `revoked_tokens` table. Expired rows are
+/// purged whenever another token is revoked.
+pub struct SqliteRevokedTokenRepository {
+    db: SqliteDatabase,
+}
+
+impl SqliteRevokedTokenRepository {
+    pub fn new(db: SqliteDatabase) -> Self {
+        SqliteRevokedTokenRepository { db }
+    }
+}
+
+#[async_trait]
+impl RevokedTokenRepository for SqliteRevokedTokenRepository {
+    async fn revoke(&self, jti: &str, expires_at: DateTime<Utc>) -> Result<(), StorageError> {
+        let jti = jti.to_string();
+        self.db.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            tx.execute("DELETE FROM revoked_tokens WHERE expires_at <= ?1", params![Utc::now()])?;
+            tx.execute(
+                "INSERT OR REPLACE INTO revoked_tokens (jti, expires_at) VALUES (?1, ?2)",
+                params![jti, expires_at],
+            )?;
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn is_revoked(&self, jti: &str) -> Result<bool, StorageError> {
+        let jti = jti.to_string();
+        self.db.with_conn(move |conn| {
+            let revoked = conn.query_row(
+                "SELECT EXISTS (SELECT 1 FROM revoked_tokens WHERE jti = ?1)",
+                params![jti],
+                |row| row.get(0),
+            )?;
+            Ok(revoked)
+        })
+        .await
+    }
+}
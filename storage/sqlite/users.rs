@@ -0,0 +1,124 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, Type, ValueRef};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+
+use crate::models::{Role, User};
+use crate::storage::{StorageError, UserPage, UserRepository};
+use super::SqliteDatabase;
+
+const USER_COLUMNS: &str = "id, email, password_hash, role, disabled_at, created_at";
+
+/// User accounts in the `users` table, whose `UNIQUE` email column turns
+/// duplicate registrations into [`StorageError::Conflict`].
+pub struct SqliteUserRepository {
+    db: SqliteDatabase,
+}
+
+impl SqliteUserRepository {
+    pub fn new(db: SqliteDatabase) -> Self {
+        SqliteUserRepository { db }
+    }
+
+    /// Sets one column of the user, returning the updated user.
+    async fn set_column<T: ToSql + Send + 'static>(
+        &self,
+        id: ObjectId,
+        column: &'static str,
+        value: T,
+    ) -> Result<Option<User>, StorageError> {
+        self.db.with_conn(move |conn| {
+            conn.execute(&format!("UPDATE users SET {} = ?2 WHERE id = ?1", column), params![id.to_hex(), value])?;
+            find_user(conn, "id", id.to_hex())
+        })
+        .await
+    }
+}
+
+impl ToSql for Role {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for Role {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value.as_str()?.parse().map_err(|err: String| FromSqlError::Other(err.into()))
+    }
+}
+
+fn row_to_user(row: &Row) -> rusqlite::Result<User> {
+    let id: String = row.get("id")?;
+    Ok(User {
+        id: ObjectId::parse_str(&id)
+            .map_err(|err| rusqlite::Error::FromSqlConversionFailure(0, Type::Text, Box::new(err)))?,
+        email: row.get("email")?,
+        password_hash: row.get("password_hash")?,
+        role: row.get("role")?,
+        disabled_at: row.get("disabled_at")?,
+        created_at: row.get("created_at")?,
+    })
+}
+
+fn find_user(conn: &Connection, column: &str, value: String) -> Result<Option<User>, StorageError> {
+    let user = conn
+        .query_row(
+            &format!("SELECT {} FROM users WHERE {} = ?1", USER_COLUMNS, column),
+            params![value],
+            row_to_user,
+        )
+        .optional()?;
+    Ok(user)
+}
+
+#[async_trait]
+impl UserRepository for SqliteUserRepository {
+    async fn create(&self, user: &User) -> Result<(), StorageError> {
+        let user = user.clone();
+        self.db.with_conn(move |conn| {
+            conn.execute(
+                &format!("INSERT INTO users ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6)", USER_COLUMNS),
+                params![user.id.to_hex(), user.email, user.password_hash, user.role, user.disabled_at, user.created_at],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get(&self, id: ObjectId) -> Result<Option<User>, StorageError> {
+        self.db.with_conn(move |conn| find_user(conn, "id", id.to_hex())).await
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, StorageError> {
+        let email = email.to_string();
+        self.db.with_conn(move |conn| find_user(conn, "email", email)).await
+    }
+
+    async fn list(&self, offset: u64, limit: u64) -> Result<UserPage, StorageError> {
+        self.db.with_conn(move |conn| {
+            let total: i64 = conn.query_row("SELECT COUNT(*) FROM users", [], |row| row.get(0))?;
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {} FROM users ORDER BY id LIMIT ?1 OFFSET ?2",
+                USER_COLUMNS
+            ))?;
+            let users = stmt
+                .query_map(params![limit as i64, offset as i64], row_to_user)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(UserPage { users, total: total as u64 })
+        })
+        .await
+    }
+
+    async fn set_role(&self, id: ObjectId, role: Role) -> Result<Option<User>, StorageError> {
+        self.set_column(id, "role", role).await
+    }
+
+    async fn set_disabled(
+        &self,
+        id: ObjectId,
+        disabled_at: Option<DateTime<Utc>>,
+    ) -> Result<Option<User>, StorageError> {
+        self.set_column(id, "disabled_at", disabled_at).await
+    }
+}
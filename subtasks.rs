@@ -0,0 +1,94 @@
+use mongodb::bson::oid::ObjectId;
+
+use crate::errors::ApiError;
+use crate::models::{Progress, Task, TaskChanges, TaskInput};
+use crate::storage::{nested_too_deep, StorageError, TaskRepository, TaskScope};
+use crate::validation::FieldErrors;
+
+/// Most parents a subtask can have above it.
+pub const MAX_SUBTASK_DEPTH: usize = 5;
+
+fn invalid_parent(message: impl Into<String>) -> ApiError {
+    let mut errors = FieldErrors::default();
+    errors.add("parent_id", message);
+    ApiError::Validation(errors)
+}
+
+/// Checks that `parent` can hold subtasks in the list `list_id`: it has to be
+/// a task in `scope` and the same list.
+async fn find_parent(
+    tasks: &dyn TaskRepository,
+    scope: &TaskScope,
+    list_id: Option<&str>,
+    parent: ObjectId,
+) -> Result<Task, ApiError> {
+    let Some(parent) = tasks.get(scope, parent).await? else {
+        return Err(invalid_parent("must be the id of a task you can edit"));
+    };
+    if parent.list_id.as_deref() != list_id {
+        return Err(invalid_parent("must be a task in the same list"));
+    }
+    Ok(parent)
+}
+
+/// Checks the parent of a task about to be created in `list`, if it has one,
+/// including that the new task is not nested too deep.
+pub async fn check_input(
+    tasks: &dyn TaskRepository,
+    scope: &TaskScope,
+    list: Option<ObjectId>,
+    input: &TaskInput,
+) -> Result<(), ApiError> {
+    let Some(parent) = input.parent_id else {
+        return Ok(());
+    };
+    let mut ancestor = find_parent(tasks, scope, list.map(|list| list.to_hex()).as_deref(), parent).await?;
+    for _ in 1..MAX_SUBTASK_DEPTH {
+        let Some(next) = ancestor.parent_id.as_deref().and_then(|id| ObjectId::parse_str(id).ok()) else {
+            return Ok(());
+        };
+        let Some(next) = tasks.get(scope, next).await? else {
+            return Ok(());
+        };
+        ancestor = next;
+    }
+    match ancestor.parent_id {
+        Some(_) => Err(nested_too_deep().into()),
+        None => Ok(()),
+    }
+}
+
+/// Checks the parent `changes` give task `id`, if they set one. A task
+/// outside of `scope` is left for the update itself to report, and so is
+/// where the task and its subtasks would end up in the tree, which
+/// [`TaskRepository::update`] checks as part of the write.
+pub async fn check_changes(
+    tasks: &dyn TaskRepository,
+    scope: &TaskScope,
+    id: ObjectId,
+    changes: &TaskChanges,
+) -> Result<(), ApiError> {
+    let Some(Some(parent)) = changes.parent_id else {
+        return Ok(());
+    };
+    match tasks.get(scope, id).await? {
+        Some(task) => find_parent(tasks, scope, task.list_id.as_deref(), parent).await.map(|_| ()),
+        None => Ok(()),
+    }
+}
+
+/// Fills in the progress of each listed task from its checklist and its
+/// direct subtasks.
+pub async fn fill_progress(repository: &dyn TaskRepository, tasks: &mut [Task]) -> Result<(), StorageError> {
+    let ids: Vec<ObjectId> = tasks
+        .iter()
+        .filter_map(|task| task.id.as_deref())
+        .filter_map(|id| ObjectId::parse_str(id).ok())
+        .collect();
+    let subtasks = repository.subtask_progress(&ids).await?;
+    for task in tasks {
+        let subtask_progress = task.id.as_ref().and_then(|id| subtasks.get(id)).copied().unwrap_or_default();
+        task.progress = Some(Progress::of(task, subtask_progress));
+    }
+    Ok(())
+}
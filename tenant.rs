@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+use std::future::{ready, Ready};
+use std::ops::Deref;
+use std::sync::Arc;
+
+use actix_web::dev::Payload;
+use actix_web::{web, FromRequest, HttpMessage, HttpRequest};
+
+use crate::auth;
+use crate::errors::ApiError;
+use crate::AppState;
+
+/// Header naming the tenant of a request.
+pub const TENANT_HEADER: &str = "X-Tenant";
+
+/// Tenant ids end up in database and file names, so they are kept to
+/// lowercase letters, digits and dashes.
+const MAX_TENANT_LENGTH: usize = 63;
+
+/// How the MongoDB backend keeps tenants apart, set with `TENANT_STRATEGY`.
+/// The other backends always give each tenant a database of its own.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TenantStrategy {
+    /// All tenants share the collections, with a `tenant_id` on every document.
+    Shared,
+    /// Each tenant gets its own database.
+    Database,
+}
+
+impl TenantStrategy {
+    pub fn from_env() -> Self {
+        match std::env::var("TENANT_STRATEGY").as_deref() {
+            Ok("shared") => TenantStrategy::Shared,
+            Ok("database") | Err(_) => TenantStrategy::Database,
+            Ok(other) => panic!("Unknown TENANT_STRATEGY '{}', expected 'shared' or 'database'", other),
+        }
+    }
+}
+
+pub fn is_valid_tenant(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_TENANT_LENGTH
+        && !id.starts_with('-')
+        && id.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+/// Reads the comma-separated tenant ids of `TENANTS`, panicking on invalid
+/// ones. Empty when the server hosts a single organization.
+pub fn tenants_from_env() -> Vec<String> {
+    let tenants: Vec<String> = std::env::var("TENANTS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|tenant| tenant.trim().to_lowercase())
+        .filter(|tenant| !tenant.is_empty())
+        .collect();
+    if let Some(invalid) = tenants.iter().find(|tenant| !is_valid_tenant(tenant)) {
+        panic!("Invalid tenant id '{}' in TENANTS", invalid);
+    }
+    tenants
+}
+
+/// The state of every tenant the server hosts, registered as app data.
+pub enum Tenants {
+    /// `TENANTS` is unset: every request uses the one state.
+    Single(Arc<AppState>),
+    Multi {
+        states: HashMap<String, Arc<AppState>>,
+        /// From `TENANT_BASE_DOMAIN`; requests to `<tenant>.<base domain>`
+        /// are for that tenant.
+        base_domain: Option<String>,
+    },
+}
+
+impl Tenants {
+    /// Picks the tenant of a request from its `X-Tenant` header or its
+    /// subdomain, or else from the `tid` claim of its access token. A token
+    /// issued for another tenant than the one requested is refused.
+    fn resolve(&self, req: &HttpRequest) -> Result<Arc<AppState>, ApiError> {
+        let (states, base_domain) = match self {
+            Tenants::Single(state) => return Ok(Arc::clone(state)),
+            Tenants::Multi { states, base_domain } => (states, base_domain),
+        };
+
+        let requested = requested_tenant(req, base_domain.as_deref());
+        let tenant = match (requested, auth::tenant_claim(req)) {
+            (Some(requested), Some(claimed)) if requested != claimed => {
+                return Err(ApiError::Forbidden("The access token was issued for another tenant".to_string()));
+            }
+            (Some(tenant), _) | (None, Some(tenant)) => tenant,
+            (None, None) => {
+                return Err(ApiError::InvalidRequest(vec![format!(
+                    "a tenant is required: send '{}' or use a tenant subdomain",
+                    TENANT_HEADER
+                )]));
+            }
+        };
+        states.get(&tenant).cloned().ok_or(ApiError::NotFound("tenant"))
+    }
+}
+
+fn requested_tenant(req: &HttpRequest, base_domain: Option<&str>) -> Option<String> {
+    if let Some(value) = req.headers().get(TENANT_HEADER) {
+        return Some(value.to_str().unwrap_or_default().trim().to_lowercase());
+    }
+
+    let base_domain = base_domain?;
+    let info = req.connection_info();
+    let host = info.host().split(':').next().unwrap_or_default().to_lowercase();
+    let subdomain = host.strip_suffix(base_domain)?.strip_suffix('.')?;
+    (!subdomain.contains('.')).then(|| subdomain.to_string())
+}
+
+/// The state of the tenant a request is for. Handlers take it in place of
+/// `web::Data<AppState>`; it is resolved once per request.
+#[derive(Clone)]
+pub struct TenantState(Arc<AppState>);
+
+impl TenantState {
+    pub fn of(req: &HttpRequest) -> Result<Self, ApiError> {
+        if let Some(state) = req.extensions().get::<TenantState>() {
+            return Ok(state.clone());
+        }
+        let tenants = req.app_data::<web::Data<Tenants>>().expect("Tenants is registered");
+        let state = TenantState(tenants.resolve(req)?);
+        req.extensions_mut().insert(state.clone());
+        Ok(state)
+    }
+}
+
+impl Deref for TenantState {
+    type Target = AppState;
+
+    fn deref(&self) -> &AppState {
+        &self.0
+    }
+}
+
+impl FromRequest for TenantState {
+    type Error = ApiError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(TenantState::of(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::header;
+    use chrono::Duration;
+    use actix_web::test::TestRequest;
+    use mongodb::bson::oid::ObjectId;
+
+    use super::*;
+    use crate::auth::AuthConfig;
+    use crate::jwt::JwtSettings;
+    use crate::Backend;
+
+    async fn tenants() -> Tenants {
+        let mut states = HashMap::new();
+        for tenant in ["acme", "globex"] {
+            let state = Backend::Memory.open(Some(tenant), Duration::hours(1)).await;
+            states.insert(tenant.to_string(), Arc::new(state));
+        }
+        Tenants::Multi { states, base_domain: Some("example.com".to_string()) }
+    }
+
+    /// A request with the given headers, its access token issued for `claimed`.
+    fn request(headers: &[(&str, &str)], claimed: Option<&str>) -> HttpRequest {
+        std::env::set_var("JWT_SECRET", "a-test-secret-of-at-least-32-bytes");
+        let jwt = JwtSettings::from_env();
+        let (token, _) = jwt.issue(ObjectId::new(), ObjectId::new(), claimed).unwrap();
+        let config = AuthConfig { jwt, refresh_ttl: Duration::days(1), admin_emails: Vec::new() };
+        let mut request = TestRequest::default().app_data(web::Data::new(config));
+        if claimed.is_some() {
+            request = request.insert_header((header::AUTHORIZATION, format!("Bearer {}", token)));
+        }
+        for &(name, value) in headers {
+            request = request.insert_header((name, value));
+        }
+        request.to_http_request()
+    }
+
+    fn resolved(tenants: &Tenants, req: &HttpRequest) -> Result<String, ApiError> {
+        tenants.resolve(req).map(|state| state.tenant.clone().unwrap())
+    }
+
+    #[actix_web::test]
+    async fn takes_the_tenant_from_the_header_subdomain_or_token() {
+        let tenants = tenants().await;
+        let by_header = request(&[(TENANT_HEADER, "Acme")], None);
+        assert_eq!(resolved(&tenants, &by_header).unwrap(), "acme");
+        let by_host = request(&[("host", "globex.example.com:8080")], None);
+        assert_eq!(resolved(&tenants, &by_host).unwrap(), "globex");
+        let by_token = request(&[], Some("globex"));
+        assert_eq!(resolved(&tenants, &by_token).unwrap(), "globex");
+        let agreeing = request(&[(TENANT_HEADER, "acme")], Some("acme"));
+        assert_eq!(resolved(&tenants, &agreeing).unwrap(), "acme");
+    }
+
+    #[actix_web::test]
+    async fn refuses_a_token_issued_for_another_tenant() {
+        let tenants = tenants().await;
+        let by_header = request(&[(TENANT_HEADER, "acme")], Some("globex"));
+        assert!(matches!(resolved(&tenants, &by_header), Err(ApiError::Forbidden(_))));
+        let by_host = request(&[("host", "acme.example.com")], Some("globex"));
+        assert!(matches!(resolved(&tenants, &by_host), Err(ApiError::Forbidden(_))));
+    }
+
+    #[actix_web::test]
+    async fn needs_a_known_tenant() {
+        let tenants = tenants().await;
+        assert!(matches!(resolved(&tenants, &request(&[], None)), Err(ApiError::InvalidRequest(_))));
+        let unknown = request(&[(TENANT_HEADER, "initech")], None);
+        assert!(matches!(resolved(&tenants, &unknown), Err(ApiError::NotFound("tenant"))));
+    }
+
+    #[test]
+    fn accepts_lowercase_letters_digits_and_dashes() {
+        assert!(is_valid_tenant("acme"));
+        assert!(is_valid_tenant("a-1"));
+        assert!(is_valid_tenant(&"a".repeat(MAX_TENANT_LENGTH)));
+    }
+
+    #[test]
+    fn rejects_malformed_ids() {
+        for id in ["", "-acme", "Acme", "ac_me", "ac.me"] {
+            assert!(!is_valid_tenant(id), "{id:?} should be rejected");
+        }
+        assert!(!is_valid_tenant(&"a".repeat(MAX_TENANT_LENGTH + 1)));
+    }
+}
@@ -0,0 +1,585 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::Serialize;
+
+use crate::models::{
+    ApiKeyPayload, ChecklistItemChanges, ChecklistItemPayload, Credentials, ListPayload, ListRole, MemberPayload,
+    Priority, Scope, TagMergePayload, TagRenamePayload, TagsPayload, TaskChanges, TaskInput, TaskPayload,
+};
+
+pub const MAX_TITLE_LENGTH: usize = 200;
+pub const MAX_DESCRIPTION_LENGTH: usize = 5000;
+pub const MAX_EMAIL_LENGTH: usize = 254;
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+/// Bounds the work of hashing a password.
+pub const MAX_PASSWORD_LENGTH: usize = 128;
+/// Longest name of an API key or a list.
+pub const MAX_NAME_LENGTH: usize = 100;
+pub const MAX_TAG_LENGTH: usize = 50;
+/// Most tags a single task can carry.
+pub const MAX_TAGS: usize = 20;
+pub const MAX_CHECKLIST_TEXT_LENGTH: usize = 500;
+/// Most items in the checklist of a single task.
+pub const MAX_CHECKLIST_ITEMS: usize = 100;
+
+/// Validation messages keyed by the offending field.
+#[derive(Debug, Default, Serialize)]
+#[serde(transparent)]
+pub struct FieldErrors(BTreeMap<String, Vec<String>>);
+
+impl FieldErrors {
+    pub fn add(&mut self, field: &str, message: impl Into<String>) {
+        self.0.entry(field.to_string()).or_default().push(message.into());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Records the error of `result` under `field`, passing the value through.
+    pub fn check<T>(&mut self, field: &str, result: Result<T, String>) -> Option<T> {
+        result.map_err(|message| self.add(field, message)).ok()
+    }
+}
+
+impl TaskPayload {
+    /// Checks the payload of a create (`POST`) or replace (`PUT`) request and
+    /// turns it into a normalized [`TaskInput`]. Ids are assigned by the
+    /// server, so a client-supplied `_id` is rejected when creating.
+    pub fn validate(self, creating: bool) -> Result<TaskInput, FieldErrors> {
+        let mut errors = FieldErrors::default();
+
+        if creating && self.id.is_some() {
+            errors.add("_id", "must not be supplied, ids are assigned by the server");
+        }
+        let title = match self.title {
+            Some(title) => errors.check("title", title_value(&title)),
+            None => {
+                errors.add("title", "is required");
+                None
+            }
+        };
+        let description = errors.check("description", description_value(self.description.as_deref()));
+        let due_at = errors.check("due_at", due_at_value(self.due_at.as_deref()));
+        let priority = errors.check("priority", priority_value(self.priority.as_deref()));
+        let tags = errors.check("tags", tags_value(self.tags.as_deref().unwrap_or_default()));
+        let parent_id = errors.check("parent_id", parent_id_value(self.parent_id.as_deref()));
+
+        match (title, description, due_at, priority, tags, parent_id) {
+            (Some(title), Some(description), Some(due_at), Some(priority), Some(tags), Some(parent_id))
+                if errors.is_empty() =>
+            {
+                Ok(TaskInput { title, description, completed: self.completed, due_at, priority, tags, parent_id })
+            }
+            _ => Err(errors),
+        }
+    }
+}
+
+impl Credentials {
+    /// Checks the credentials of a new account, returning the normalized
+    /// email and the password.
+    pub fn validate(self) -> Result<(String, String), FieldErrors> {
+        let mut errors = FieldErrors::default();
+
+        let email = match self.email {
+            Some(email) => errors.check("email", email_value(&email)),
+            None => {
+                errors.add("email", "is required");
+                None
+            }
+        };
+        let password = match self.password {
+            Some(password) => errors.check("password", password_value(password)),
+            None => {
+                errors.add("password", "is required");
+                None
+            }
+        };
+
+        match (email, password) {
+            (Some(email), Some(password)) => Ok((email, password)),
+            _ => Err(errors),
+        }
+    }
+}
+
+impl ApiKeyPayload {
+    /// Checks the payload of a new API key, returning its trimmed name and its
+    /// scopes, sorted and without duplicates.
+    pub fn validate(self) -> Result<(String, Vec<Scope>), FieldErrors> {
+        let mut errors = FieldErrors::default();
+
+        let name = match self.name {
+            Some(name) => errors.check("name", name_value(&name)),
+            None => {
+                errors.add("name", "is required");
+                None
+            }
+        };
+        let scopes = match self.scopes {
+            Some(scopes) => errors.check("scopes", scopes_value(&scopes)),
+            None => {
+                errors.add("scopes", "is required");
+                None
+            }
+        };
+
+        match (name, scopes) {
+            (Some(name), Some(scopes)) => Ok((name, scopes)),
+            _ => Err(errors),
+        }
+    }
+}
+
+impl ListPayload {
+    /// Checks the payload of a new or renamed list, returning its trimmed name.
+    pub fn validate(self) -> Result<String, FieldErrors> {
+        let mut errors = FieldErrors::default();
+        let name = match self.name {
+            Some(name) => errors.check("name", name_value(&name)),
+            None => {
+                errors.add("name", "is required");
+                None
+            }
+        };
+        name.ok_or(errors)
+    }
+}
+
+impl MemberPayload {
+    /// Checks who to share a list with and in which role, returning the
+    /// normalized email and the role.
+    pub fn validate(self) -> Result<(String, ListRole), FieldErrors> {
+        let mut errors = FieldErrors::default();
+
+        let email = match self.email {
+            Some(email) => errors.check("email", email_value(&email)),
+            None => {
+                errors.add("email", "is required");
+                None
+            }
+        };
+        let role = match self.role {
+            Some(role) => errors.check(
+                "role",
+                role.parse::<ListRole>().map_err(|_| "must be one of viewer, editor, owner".to_string()),
+            ),
+            None => {
+                errors.add("role", "is required");
+                None
+            }
+        };
+
+        match (email, role) {
+            (Some(email), Some(role)) => Ok((email, role)),
+            _ => Err(errors),
+        }
+    }
+}
+
+impl TagsPayload {
+    /// Checks the tags to add to a task, returning them normalized.
+    pub fn validate(self) -> Result<Vec<String>, FieldErrors> {
+        let mut errors = FieldErrors::default();
+        let tags = match self.tags {
+            Some(tags) => errors.check("tags", required_tags_value(&tags)),
+            None => {
+                errors.add("tags", "is required");
+                None
+            }
+        };
+        tags.ok_or(errors)
+    }
+}
+
+impl TagRenamePayload {
+    /// Checks the new name of a tag, returning it normalized.
+    pub fn validate(self) -> Result<String, FieldErrors> {
+        let mut errors = FieldErrors::default();
+        let name = match self.name {
+            Some(name) => errors.check("name", tag_value(&name)),
+            None => {
+                errors.add("name", "is required");
+                None
+            }
+        };
+        name.ok_or(errors)
+    }
+}
+
+impl TagMergePayload {
+    /// Checks which tags to merge and into which, returning them normalized.
+    pub fn validate(self) -> Result<(Vec<String>, String), FieldErrors> {
+        let mut errors = FieldErrors::default();
+
+        let tags = match self.tags {
+            Some(tags) => errors.check("tags", required_tags_value(&tags)),
+            None => {
+                errors.add("tags", "is required");
+                None
+            }
+        };
+        let into = match self.into {
+            Some(into) => errors.check("into", tag_value(&into)),
+            None => {
+                errors.add("into", "is required");
+                None
+            }
+        };
+
+        match (tags, into) {
+            (Some(tags), Some(into)) => Ok((tags, into)),
+            _ => Err(errors),
+        }
+    }
+}
+
+impl ChecklistItemPayload {
+    /// Checks a new checklist item, returning its trimmed text and where to
+    /// insert it.
+    pub fn validate(self) -> Result<(String, Option<u32>), FieldErrors> {
+        let mut errors = FieldErrors::default();
+        let text = match self.text {
+            Some(text) => errors.check("text", checklist_text_value(&text)),
+            None => {
+                errors.add("text", "is required");
+                None
+            }
+        };
+        text.map(|text| (text, self.position)).ok_or(errors)
+    }
+}
+
+/// Turns a JSON merge patch of a checklist item into item changes, the way
+/// [`merge_patch`] does for tasks.
+pub fn checklist_item_patch(
+    patch: serde_json::Map<String, serde_json::Value>,
+) -> Result<ChecklistItemChanges, FieldErrors> {
+    let mut errors = FieldErrors::default();
+    let mut changes = ChecklistItemChanges::default();
+
+    for (field, value) in patch {
+        match field.as_str() {
+            "text" => match value.as_str() {
+                Some(text) => changes.text = errors.check(&field, checklist_text_value(text)),
+                None => errors.add(&field, "must be a string"),
+            },
+            "done" => match value.as_bool() {
+                Some(done) => changes.done = Some(done),
+                None => errors.add(&field, "must be a boolean"),
+            },
+            "position" => match value.as_u64().and_then(|position| u32::try_from(position).ok()) {
+                Some(position) => changes.position = Some(position),
+                None => errors.add(&field, "must be a non-negative integer"),
+            },
+            _ => errors.add(&field, "is not an editable field"),
+        }
+    }
+
+    if errors.is_empty() { Ok(changes) } else { Err(errors) }
+}
+
+/// Turns an RFC 7396 JSON merge patch into task changes. Only members present
+/// in the patch are changed, and `null` clears an optional field. Members
+/// that are not user-editable fields are rejected rather than ignored.
+pub fn merge_patch(patch: serde_json::Map<String, serde_json::Value>) -> Result<TaskChanges, FieldErrors> {
+    let mut errors = FieldErrors::default();
+    let mut changes = TaskChanges::default();
+
+    for (field, value) in patch {
+        match field.as_str() {
+            "title" => match value.as_str() {
+                Some(title) => changes.title = errors.check(&field, title_value(title)),
+                None => errors.add(&field, "must be a string"),
+            },
+            "completed" => match value.as_bool() {
+                Some(completed) => changes.completed = Some(completed),
+                None => errors.add(&field, "must be a boolean"),
+            },
+            "description" => {
+                changes.description = optional_string(&mut errors, &field, &value, description_value)
+            }
+            "due_at" => changes.due_at = optional_string(&mut errors, &field, &value, due_at_value),
+            "priority" => changes.priority = optional_string(&mut errors, &field, &value, priority_value),
+            "tags" => changes.tags = errors.check(&field, patch_tags_value(&value)),
+            "parent_id" => changes.parent_id = optional_string(&mut errors, &field, &value, parent_id_value),
+            _ => errors.add(&field, "is not an editable field"),
+        }
+    }
+
+    if errors.is_empty() { Ok(changes) } else { Err(errors) }
+}
+
+/// Validates a nullable string member of a merge patch with `validate`, which
+/// receives `None` for `null`.
+fn optional_string<T>(
+    errors: &mut FieldErrors,
+    field: &str,
+    value: &serde_json::Value,
+    validate: fn(Option<&str>) -> Result<Option<T>, String>,
+) -> Option<Option<T>> {
+    match value {
+        serde_json::Value::Null => errors.check(field, validate(None)),
+        serde_json::Value::String(value) => errors.check(field, validate(Some(value))),
+        _ => {
+            errors.add(field, "must be a string or null");
+            None
+        }
+    }
+}
+
+/// The `tags` member of a merge patch, where `null` removes every tag.
+fn patch_tags_value(value: &serde_json::Value) -> Result<Vec<String>, String> {
+    let invalid = || "must be an array of strings or null".to_string();
+    match value {
+        serde_json::Value::Null => Ok(Vec::new()),
+        serde_json::Value::Array(items) => {
+            let tags: Option<Vec<&str>> = items.iter().map(|item| item.as_str()).collect();
+            tags_value(&tags.ok_or_else(invalid)?)
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// Titles are trimmed and must not end up empty.
+pub fn title_value(title: &str) -> Result<String, String> {
+    let title = title.trim();
+    if title.is_empty() {
+        return Err("must not be empty".to_string());
+    }
+    if title.chars().count() > MAX_TITLE_LENGTH {
+        return Err(format!("must be at most {} characters", MAX_TITLE_LENGTH));
+    }
+    Ok(title.to_string())
+}
+
+/// Descriptions are trimmed; a blank one is stored as no description.
+pub fn description_value(description: Option<&str>) -> Result<Option<String>, String> {
+    let description = description.map(str::trim).filter(|description| !description.is_empty());
+    match description {
+        Some(description) if description.chars().count() > MAX_DESCRIPTION_LENGTH => {
+            Err(format!("must be at most {} characters", MAX_DESCRIPTION_LENGTH))
+        }
+        _ => Ok(description.map(str::to_string)),
+    }
+}
+
+pub fn due_at_value(due_at: Option<&str>) -> Result<Option<DateTime<Utc>>, String> {
+    due_at
+        .map(|due_at| {
+            DateTime::parse_from_rfc3339(due_at)
+                .map(|due_at| due_at.with_timezone(&Utc))
+                .map_err(|_| "must be an RFC 3339 timestamp".to_string())
+        })
+        .transpose()
+}
+
+pub fn priority_value(priority: Option<&str>) -> Result<Option<Priority>, String> {
+    priority
+        .map(|priority| {
+            priority
+                .parse()
+                .map_err(|_| "must be one of low, medium, high, urgent".to_string())
+        })
+        .transpose()
+}
+
+pub fn parent_id_value(parent_id: Option<&str>) -> Result<Option<ObjectId>, String> {
+    parent_id
+        .map(|parent_id| ObjectId::parse_str(parent_id).map_err(|_| "must be a task id".to_string()))
+        .transpose()
+}
+
+/// Checklist items are trimmed and must not end up empty.
+pub fn checklist_text_value(text: &str) -> Result<String, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("must not be empty".to_string());
+    }
+    if text.chars().count() > MAX_CHECKLIST_TEXT_LENGTH {
+        return Err(format!("must be at most {} characters", MAX_CHECKLIST_TEXT_LENGTH));
+    }
+    Ok(text.to_string())
+}
+
+/// Emails are trimmed and lowercased. Only the basic `local@domain` shape is
+/// checked; whether the address works is not.
+pub fn email_value(email: &str) -> Result<String, String> {
+    let email = email.trim().to_lowercase();
+    if email.len() > MAX_EMAIL_LENGTH {
+        return Err(format!("must be at most {} characters", MAX_EMAIL_LENGTH));
+    }
+    match email.split_once('@') {
+        Some((local, domain)) if !local.is_empty() && domain.contains('.') && !domain.contains('@') => Ok(email),
+        _ => Err("must be an email address".to_string()),
+    }
+}
+
+pub fn password_value(password: String) -> Result<String, String> {
+    let length = password.chars().count();
+    if length < MIN_PASSWORD_LENGTH {
+        return Err(format!("must be at least {} characters", MIN_PASSWORD_LENGTH));
+    }
+    if length > MAX_PASSWORD_LENGTH {
+        return Err(format!("must be at most {} characters", MAX_PASSWORD_LENGTH));
+    }
+    Ok(password)
+}
+
+pub fn name_value(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("must not be empty".to_string());
+    }
+    if name.chars().count() > MAX_NAME_LENGTH {
+        return Err(format!("must be at most {} characters", MAX_NAME_LENGTH));
+    }
+    Ok(name.to_string())
+}
+
+/// Tags are trimmed and lowercased, and kept to letters, digits, `-` and `_`
+/// so that they can be listed in a query string.
+pub fn tag_value(tag: &str) -> Result<String, String> {
+    let tag = tag.trim().to_lowercase();
+    if tag.is_empty() {
+        return Err("must not be empty".to_string());
+    }
+    if tag.chars().count() > MAX_TAG_LENGTH {
+        return Err(format!("must be at most {} characters", MAX_TAG_LENGTH));
+    }
+    if !tag.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_') {
+        return Err("must only contain letters, digits, '-' and '_'".to_string());
+    }
+    Ok(tag)
+}
+
+/// Normalizes every tag, dropping duplicates but keeping the order.
+pub fn tags_value<S: AsRef<str>>(tags: &[S]) -> Result<Vec<String>, String> {
+    let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags {
+        let tag = tag_value(tag.as_ref()).map_err(|message| format!("'{}' {}", tag.as_ref(), message))?;
+        if !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    if normalized.len() > MAX_TAGS {
+        return Err(format!("must contain at most {} tags", MAX_TAGS));
+    }
+    Ok(normalized)
+}
+
+fn required_tags_value(tags: &[String]) -> Result<Vec<String>, String> {
+    let tags = tags_value(tags)?;
+    if tags.is_empty() {
+        return Err("must contain at least one tag".to_string());
+    }
+    Ok(tags)
+}
+
+pub fn scopes_value(scopes: &[String]) -> Result<Vec<Scope>, String> {
+    let mut parsed = scopes
+        .iter()
+        .map(|scope| scope.parse::<Scope>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| "must only contain tasks:read, tasks:write".to_string())?;
+    if parsed.is_empty() {
+        return Err("must contain at least one scope".to_string());
+    }
+    parsed.sort();
+    parsed.dedup();
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn patch(value: Value) -> Result<TaskChanges, FieldErrors> {
+        match value {
+            Value::Object(members) => merge_patch(members),
+            _ => panic!("a merge patch is a JSON object"),
+        }
+    }
+
+    fn failed_fields(value: Value) -> Vec<String> {
+        match patch(value) {
+            Ok(_) => panic!("the patch should have been rejected"),
+            Err(errors) => errors.0.into_keys().collect(),
+        }
+    }
+
+    #[test]
+    fn only_present_members_change() {
+        let changes = patch(json!({ "title": "  New title ", "completed": true })).unwrap();
+        assert_eq!(changes.title.as_deref(), Some("New title"));
+        assert_eq!(changes.completed, Some(true));
+        assert!(changes.description.is_none());
+        assert!(changes.due_at.is_none());
+        assert!(changes.priority.is_none());
+        assert!(changes.tags.is_none());
+        assert!(changes.parent_id.is_none());
+    }
+
+    #[test]
+    fn null_clears_optional_fields() {
+        let changes = patch(json!({
+            "description": null,
+            "due_at": null,
+            "priority": null,
+            "tags": null,
+            "parent_id": null,
+        }))
+        .unwrap();
+        assert_eq!(changes.description, Some(None));
+        assert_eq!(changes.due_at, Some(None));
+        assert_eq!(changes.priority, Some(None));
+        assert_eq!(changes.tags, Some(Vec::new()));
+        assert_eq!(changes.parent_id, Some(None));
+    }
+
+    #[test]
+    fn values_are_normalized() {
+        let parent = ObjectId::new();
+        let changes = patch(json!({
+            "description": "   ",
+            "due_at": "2026-03-01T12:00:00+02:00",
+            "priority": "high",
+            "tags": ["Backend", "backend", "urgent"],
+            "parent_id": parent.to_hex(),
+        }))
+        .unwrap();
+        assert_eq!(changes.description, Some(None));
+        assert_eq!(changes.due_at, Some(Some("2026-03-01T10:00:00Z".parse().unwrap())));
+        assert_eq!(changes.priority, Some(Some(Priority::High)));
+        assert_eq!(changes.tags, Some(vec!["backend".to_string(), "urgent".to_string()]));
+        assert_eq!(changes.parent_id, Some(Some(parent)));
+    }
+
+    #[test]
+    fn every_invalid_member_is_reported() {
+        let fields = failed_fields(json!({
+            "title": " ",
+            "completed": "yes",
+            "due_at": "tomorrow",
+            "priority": 3,
+            "tags": ["ok", 1],
+            "parent_id": "nope",
+        }));
+        assert_eq!(fields, ["completed", "due_at", "parent_id", "priority", "tags", "title"]);
+    }
+
+    #[test]
+    fn unknown_and_read_only_members_are_rejected() {
+        assert_eq!(failed_fields(json!({ "_id": "x", "revision": 3, "color": "red" })), ["_id", "color", "revision"]);
+    }
+
+    #[test]
+    fn title_cannot_be_cleared() {
+        assert_eq!(failed_fields(json!({ "title": null })), ["title"]);
+    }
+}